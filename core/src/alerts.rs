@@ -0,0 +1,103 @@
+//! The alerts mod decides which [`Food`]s the user should be warned about, because they
+//! have expired or are about to. What the user dismissed or snoozed is persisted, so that
+//! the same alert doesn't pop up every time the app starts.
+
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+
+use super::backend::{Food, FoodId, FoodState};
+use super::clock;
+use super::config;
+use super::log;
+
+/// How bad the state of an alerted [`Food`] is
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AlertLevel {
+    Expiring,
+    Expired,
+}
+
+impl AlertLevel {
+    /// The [`AlertLevel`] of a [`Food`], [`None`] when far from expiring
+    #[inline]
+    pub fn of(food: &Food) -> Option<Self> {
+        match food.best_before.state() {
+            FoodState::FarFromExpiring => None,
+            FoodState::CloseFromExpiring => Some(Self::Expiring),
+            FoodState::Expired => Some(Self::Expired),
+        }
+    }
+}
+
+/// The [`Alerts`] remember the dismissed [`Food`]s and until when the alerts are snoozed
+#[derive(Deserialize, Serialize, Default)]
+pub struct Alerts {
+    /// Dismissed [`Food`]s, with the level they were dismissed at. A [`Food`] dismissed
+    /// while expiring is alerted again once expired
+    dismissed: BTreeMap<FoodId, AlertLevel>,
+
+    /// No alert until this time, in milliseconds since the Unix epoch
+    snoozed_until: Option<i64>,
+}
+
+impl Alerts {
+    /// Open the [`Alerts`]. A missing file simply means nothing has been dismissed, while
+    /// any other error is logged and the process aborts
+    pub fn open() -> Self {
+        let file = match fs::File::open(&config::current().paths.alerts) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Self::default(),
+            Err(err) => log::fatal(err),
+        };
+        serde_json::from_reader(file).unwrap_or_else(|err| log::fatal(err))
+    }
+
+    /// Update the [`Alerts`], overwriting the contents of the json file
+    pub fn update(&self) {
+        let contents = serde_json::to_string_pretty(self).unwrap_or_else(|err| log::fatal(err));
+        fs::write(&config::current().paths.alerts, contents).unwrap_or_else(|err| log::fatal(err));
+    }
+
+    /// The [`Food`]s to alert about, expired ones first, leaving out the dismissed ones
+    pub fn pending(&self, foods: impl IntoIterator<Item = Food>) -> Vec<(Food, AlertLevel)> {
+        let mut pending = foods
+            .into_iter()
+            .filter_map(|food| Some((AlertLevel::of(&food)?, food)))
+            .filter(|(level, food)| self.dismissed.get(&food.id) < Some(level))
+            .map(|(level, food)| (food, level))
+            .collect::<Vec<_>>();
+        pending.sort_by(|(a, a_level), (b, b_level)| b_level.cmp(a_level).then(a.cmp(b)));
+        pending
+    }
+
+    /// Returns whether the alerts are snoozed right now
+    #[inline]
+    pub fn is_snoozed(&self) -> bool {
+        self.snoozed_until
+            .map_or(false, |until| clock::system_millis() < until)
+    }
+
+    /// No alert for the given number of minutes
+    #[inline]
+    pub fn snooze(mut self, minutes: u32) -> Self {
+        self.snoozed_until = Some(clock::system_millis() + minutes as i64 * 60 * 1000);
+        self
+    }
+
+    /// Stop alerting about a [`Food`] until its state gets worse
+    #[inline]
+    pub fn dismiss(mut self, id: FoodId, level: AlertLevel) -> Self {
+        self.dismissed.insert(id, level);
+        self
+    }
+
+    /// Forget the dismissed [`Food`]s that are gone from every inventory, so that the file
+    /// doesn't grow forever
+    pub fn forget_missing(mut self, foods: &[Food]) -> Self {
+        self.dismissed
+            .retain(|id, _| foods.iter().any(|food| food.id == *id));
+        self
+    }
+}
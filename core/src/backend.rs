@@ -0,0 +1,1085 @@
+//! The backend mod helps the gui to run smoothly, providing multiple structs for
+//! serializing and deserializing the json fridge, adding and removing foods from it
+//! and updating it, as well as other helper functions such as [`today`]
+
+use chrono::Datelike;
+use serde_derive::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::cmp;
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::mem;
+use std::sync::{PoisonError, RwLock};
+
+use super::clock;
+use super::config;
+use super::log;
+use super::nutrition::{IntakeLog, Nutrition};
+use super::sync::Replica;
+use super::waste::WasteLog;
+
+pub type Foods = Vec<Food>;
+
+thread_local! {
+    /// Set whenever an old numeric [`FoodId`] is read, so that the file gets migrated
+    static READ_LEGACY_ID: Cell<bool> = const { Cell::new(false) };
+}
+
+/// The functions called with every change saved to a [`Fridge`], see [`on_change`]
+static LISTENERS: RwLock<Vec<Listener>> = RwLock::new(Vec::new());
+
+/// Names of the months, used in the date validation messages
+const MONTH_NAMES: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+/// A day and month typed without the year is moved to next year if it would be
+/// more than this many days in the past
+const PAST_TOLERANCE_DAYS: i64 = 30;
+
+/// A [`Food`] can have one of three states
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FoodState {
+    FarFromExpiring,   // Green
+    CloseFromExpiring, // Yellow
+    Expired,           // Red
+}
+
+impl FoodState {
+    /// Name of the [`FoodState`] in the payloads sent to other apps, such as the webhooks
+    #[inline]
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::FarFromExpiring => "far_from_expiring",
+            Self::CloseFromExpiring => "close_from_expiring",
+            Self::Expired => "expired",
+        }
+    }
+}
+
+/// What happened to a [`Food`] of a [`Fridge`], as told to the [`Listener`]s
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FoodEvent {
+    Added,
+    Opened,
+    Eaten,
+
+    /// Thrown away without being eaten
+    Discarded,
+
+    /// Used up to cook the [`Leftover`] with the given id
+    UsedIn(FoodId),
+}
+
+/// A function called with the name of the inventory, the [`FoodEvent`] and the [`Food`]
+/// as it was stored, once the change is saved
+pub type Listener = fn(&str, FoodEvent, &Food);
+
+/// The reason why a date typed by the user has been rejected
+#[derive(Debug)]
+pub enum DateError {
+    UnknownFormat,
+    InvalidMonth(u32),
+    InvalidDay { day: u32, month: u32, year: i32 },
+    InvalidOffset,
+    OutOfRange,
+}
+
+impl fmt::Display for DateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownFormat => write!(f, "Use a format like 12/03, 2026-03-12 or +5d"),
+            Self::InvalidMonth(month) => write!(f, "There is no month {}", month),
+            Self::InvalidDay { day, month, year } => write!(
+                f,
+                "{} {} has no day {}",
+                MONTH_NAMES[*month as usize - 1],
+                year,
+                day
+            ),
+            Self::InvalidOffset => write!(f, "Offsets look like +5d, +2w or +1m"),
+            Self::OutOfRange => write!(f, "The date is too far away"),
+        }
+    }
+}
+
+impl std::error::Error for DateError {}
+
+/// Why a [`Fridge`] operation failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FridgeError {
+    /// No [`Food`] has this id, it may have been removed in the meantime
+    NotFound(FoodId),
+}
+
+impl fmt::Display for FridgeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotFound(id) => write!(f, "No food with id {} in the fridge", id),
+        }
+    }
+}
+
+impl std::error::Error for FridgeError {}
+
+#[derive(Deserialize, Serialize, Eq, PartialEq, Copy, Clone)]
+pub struct BestBefore {
+    pub day: u8,
+    pub month: u8,
+
+    /// Fridges saved before the year was introduced assume the current year
+    #[serde(default = "current_year")]
+    pub year: i32,
+}
+
+/// Compare [`BestBefore`] in order to sort them in the UI
+impl PartialOrd for BestBefore {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Compare [`BestBefore`] in order to sort them in the UI
+impl Ord for BestBefore {
+    #[inline]
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        (self.year, self.month, self.day).cmp(&(other.year, other.month, other.day))
+    }
+}
+
+impl fmt::Display for BestBefore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:0>2} / {:0>2}", self.day, self.month) // Two digits padding
+    }
+}
+
+impl From<chrono::NaiveDate> for BestBefore {
+    fn from(date: chrono::NaiveDate) -> Self {
+        Self {
+            day: date.day() as u8,
+            month: date.month() as u8,
+            year: date.year(),
+        }
+    }
+}
+
+impl BestBefore {
+    #[inline]
+    pub fn new(day: u8, month: u8, year: i32) -> Self {
+        Self { day, month, year }
+    }
+
+    /// Get the [`BestBefore`] of a [`Food`] of today
+    #[inline]
+    pub fn today() -> Self {
+        Self::from(clock::today())
+    }
+
+    /// Get the [`BestBefore`] that falls the given number of days after today
+    #[inline]
+    pub fn in_days(days: u16) -> Self {
+        Self::from(clock::today() + chrono::Duration::days(days as i64))
+    }
+
+    /// Get the [`BestBefore`] that falls the given number of months after today
+    #[inline]
+    pub fn in_months(months: u32) -> Self {
+        let today = clock::today();
+        Self::from(
+            today
+                .checked_add_months(chrono::Months::new(months))
+                .unwrap_or(today),
+        )
+    }
+
+    /// Convert the [`BestBefore`] into a [`chrono::NaiveDate`]. The 29th of February saved
+    /// before the year was introduced may not exist, in which case we fall back to the
+    /// last day of the month
+    pub fn date(&self) -> chrono::NaiveDate {
+        (1..=self.day)
+            .rev()
+            .find_map(|day| {
+                chrono::NaiveDate::from_ymd_opt(self.year, self.month as u32, day as u32)
+            })
+            .unwrap_or_default()
+    }
+
+    /// Parse a date typed by the user. Accepted formats are:
+    ///   * `12/03` => day and month. The year is the current one, or the next one if the
+    ///     date would otherwise be long gone
+    ///   * `12/03/2026` => day, month and year
+    ///   * `2026-03-12` => year, month and day
+    ///   * `+5d`, `+2w`, `+1m` => days, weeks or months from today. `+5` means days
+    pub fn parse(text: &str) -> Result<Self, DateError> {
+        let text = text.trim();
+        let today = clock::today();
+
+        if let Some(offset) = text.strip_prefix('+') {
+            let (count, unit) = match offset.find(|ch: char| !ch.is_ascii_digit()) {
+                Some(i) => offset.split_at(i),
+                None => (offset, "d"),
+            };
+            let count = count.parse::<u32>().map_err(|_| DateError::InvalidOffset)?;
+            let date = match unit.trim() {
+                "d" => today.checked_add_signed(chrono::Duration::days(count as i64)),
+                "w" => today.checked_add_signed(chrono::Duration::weeks(count as i64)),
+                "m" => today.checked_add_months(chrono::Months::new(count)),
+                _ => return Err(DateError::InvalidOffset),
+            };
+            return date.map(Self::from).ok_or(DateError::OutOfRange);
+        }
+
+        let numbers = |separator: char| {
+            text.split(separator)
+                .map(|part| part.trim().parse::<u32>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| DateError::UnknownFormat)
+        };
+        let (day, month, year) = if text.contains('-') {
+            match numbers('-')?.as_slice() {
+                [year, month, day] => (*day, *month, Some(*year as i32)),
+                _ => return Err(DateError::UnknownFormat),
+            }
+        } else {
+            match numbers('/')?.as_slice() {
+                [day, month] => (*day, *month, None),
+                [day, month, year] => (*day, *month, Some(*year as i32)),
+                _ => return Err(DateError::UnknownFormat),
+            }
+        };
+
+        if !(1..=12).contains(&month) {
+            return Err(DateError::InvalidMonth(month));
+        }
+        let in_year = |year: i32| {
+            chrono::NaiveDate::from_ymd_opt(year, month, day).ok_or(DateError::InvalidDay {
+                day,
+                month,
+                year,
+            })
+        };
+        let date = match year {
+            Some(year) => in_year(year)?,
+            None => match in_year(today.year())? {
+                date if (today - date).num_days() > PAST_TOLERANCE_DAYS => {
+                    in_year(today.year() + 1)?
+                }
+                date => date,
+            },
+        };
+        Ok(Self::from(date))
+    }
+
+    /// Format the [`BestBefore`] with a [`chrono::format::strftime`] format
+    #[inline]
+    pub fn format(&self, format: &str) -> String {
+        self.date().format(format).to_string()
+    }
+
+    /// Based on the days left, return a [`FoodState`].
+    /// The thresholds come from the config, by default they are (days are inclusive):
+    ///   * Today => Expired
+    ///   * Tomorrow, 2, 3  => Close from expiring
+    ///   * 4 or more => Far from expiring
+    #[inline]
+    pub fn state(&self) -> FoodState {
+        let days_left = self.days_left();
+        let config = config::current();
+        if days_left <= config.expiry.expired_days {
+            FoodState::Expired
+        } else if days_left <= config.expiry.close_days {
+            FoodState::CloseFromExpiring
+        } else {
+            FoodState::FarFromExpiring
+        }
+    }
+
+    /// Get how many days are left from today until self.
+    /// If today is past self, then the food must be expired for sure and we return 0
+    #[inline]
+    pub fn days_left(&self) -> u16 {
+        let days_left = (self.date() - clock::today()).num_days();
+        days_left.clamp(0, u16::MAX as i64) as u16
+    }
+}
+
+/// The [`FoodId`] identifies a [`Food`] for good, across inventories and devices. It is a
+/// [ULID](https://github.com/ulid/spec), so ids sort by creation time.
+/// Fridges saved with the old numeric ids are still read: a numeric id becomes the ULID
+/// with a zero timestamp, the same on every device, and the file is rewritten with it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct FoodId(ulid::Ulid);
+
+impl FoodId {
+    /// A brand new [`FoodId`]
+    #[inline]
+    pub fn new() -> Self {
+        Self(ulid::Ulid::new())
+    }
+
+    /// Returns whether the [`Food`] hasn't been given an id by the [`Fridge`] yet
+    #[inline]
+    pub fn is_unassigned(&self) -> bool {
+        self.0.is_nil()
+    }
+}
+
+impl From<ulid::Ulid> for FoodId {
+    #[inline]
+    fn from(ulid: ulid::Ulid) -> Self {
+        Self(ulid)
+    }
+}
+
+impl fmt::Display for FoodId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl serde::Serialize for FoodId {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&self.0)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for FoodId {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Legacy(u64),
+            Ulid(String),
+        }
+
+        match Raw::deserialize(deserializer)? {
+            Raw::Legacy(id) => {
+                READ_LEGACY_ID.with(|read| read.set(true));
+                Ok(Self(ulid::Ulid::from_parts(0, id as u128 + 1)))
+            }
+            Raw::Ulid(id) => ulid::Ulid::from_string(&id)
+                .map(Self)
+                .map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+/// The [`Food`] represents a single element of the [`Fridge`].
+#[derive(Deserialize, Serialize, Eq, PartialEq, Clone)]
+pub struct Food {
+    pub name: String,
+    pub best_before: BestBefore,
+
+    /// Assigned by the [`Fridge`] when the [`Food`] is added, also used when sorting
+    /// [`Food`]s
+    pub id: FoodId,
+
+    /// true when the [`Food`] has been opened but not completely eaten
+    pub open: bool,
+
+    /// EAN/UPC barcode the [`Food`] has been scanned with, if any
+    #[serde(default)]
+    pub barcode: Option<String>,
+
+    #[serde(default)]
+    pub category: Option<String>,
+
+    /// Where the [`Food`] is stored, such as a shelf or the freezer
+    #[serde(default)]
+    pub location: Option<String>,
+
+    /// What we know about the [`Food`] when it is a leftover of a dish cooked at home
+    #[serde(default)]
+    pub leftover: Option<Leftover>,
+
+    #[serde(default)]
+    pub nutrition: Option<Nutrition>,
+
+    /// What the [`Food`] cost, in cents of the currency of the settings
+    #[serde(default)]
+    pub price: Option<u32>,
+
+    /// Where the [`Food`] was bought
+    #[serde(default)]
+    pub store: Option<String>,
+
+    /// The day the [`Food`] was added, [`None`] for the ones added before it was recorded
+    #[serde(default)]
+    pub added_on: Option<chrono::NaiveDate>,
+}
+
+/// A [`Leftover`] has no printed best before date: it is derived from the day it was
+/// cooked and the shelf life of its dish, see [`Leftovers`](config::Leftovers)
+#[derive(Deserialize, Serialize, Eq, PartialEq, Clone)]
+pub struct Leftover {
+    pub cooked_on: chrono::NaiveDate,
+
+    /// Type of dish, such as cooked rice or soup
+    pub dish: String,
+
+    /// The [`Food`]s used up to cook it
+    #[serde(default)]
+    pub ingredients: Vec<Ingredient>,
+}
+
+/// A [`Food`] used up to cook a [`Leftover`]. Its name is kept because the [`Food`] itself
+/// is gone from the [`Fridge`]
+#[derive(Deserialize, Serialize, Eq, PartialEq, Clone)]
+pub struct Ingredient {
+    pub id: FoodId,
+    pub name: String,
+}
+
+impl Leftover {
+    /// Best before date of a dish cooked on the given day, from the shelf life of its dish
+    pub fn best_before(cooked_on: chrono::NaiveDate, dish: &str) -> BestBefore {
+        let days = config::current().leftovers.shelf_life(dish);
+        BestBefore::from(cooked_on + chrono::Duration::days(days as i64))
+    }
+}
+
+/// Compare [`Food`] in order to sort them in the UI
+impl PartialOrd for Food {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Compare [`Food`] in order to sort them in the UI
+impl Ord for Food {
+    #[inline]
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        match self.best_before.cmp(&other.best_before) {
+            cmp::Ordering::Equal => match self.name.cmp(&other.name) {
+                cmp::Ordering::Equal => self.id.cmp(&other.id),
+                other => other,
+            },
+            other => other,
+        }
+    }
+}
+
+impl Food {
+    /// A new [`Food`], without an id until it is added to a [`Fridge`]
+    #[inline]
+    pub fn new(name: String, best_before: BestBefore) -> Self {
+        Self {
+            name,
+            best_before,
+            id: FoodId::default(),
+            open: false,
+            barcode: None,
+            category: None,
+            location: None,
+            leftover: None,
+            nutrition: None,
+            price: None,
+            store: None,
+            added_on: None,
+        }
+    }
+
+    /// A new leftover [`Food`] of a dish cooked on the given day, whose best before date
+    /// is derived from the shelf life of the dish
+    pub fn leftover(name: String, leftover: Leftover) -> Self {
+        Self {
+            leftover: Some(leftover.clone()),
+            ..Self::new(
+                name,
+                Leftover::best_before(leftover.cooked_on, &leftover.dish),
+            )
+        }
+    }
+
+    /// Set the barcode of the [`Food`]
+    #[inline]
+    pub fn with_barcode(mut self, barcode: Option<String>) -> Self {
+        self.barcode = barcode;
+        self
+    }
+
+    /// Set the category of the [`Food`]
+    #[inline]
+    pub fn with_category(mut self, category: Option<String>) -> Self {
+        self.category = category;
+        self
+    }
+
+    /// Set the location of the [`Food`]
+    #[inline]
+    pub fn with_location(mut self, location: Option<String>) -> Self {
+        self.location = location;
+        self
+    }
+
+    /// Set the nutrition facts of the [`Food`]
+    #[inline]
+    pub fn with_nutrition(mut self, nutrition: Option<Nutrition>) -> Self {
+        self.nutrition = nutrition;
+        self
+    }
+
+    /// Set the price of the [`Food`], in cents
+    #[inline]
+    pub fn with_price(mut self, price: Option<u32>) -> Self {
+        self.price = price;
+        self
+    }
+
+    /// Set the store the [`Food`] was bought at
+    #[inline]
+    pub fn with_store(mut self, store: Option<String>) -> Self {
+        self.store = store;
+        self
+    }
+
+    /// Record that the [`Food`] is added today
+    #[inline]
+    pub fn added_today(mut self) -> Self {
+        self.added_on = Some(clock::today());
+        self
+    }
+
+    /// Days since the [`Food`] was added, if known
+    #[inline]
+    pub fn age(&self) -> Option<u32> {
+        let days = (clock::today() - self.added_on?).num_days();
+        Some(days.max(0) as u32)
+    }
+
+    /// Returns whether the [`Food`] has been stored for long while its best before is
+    /// still far away, as freezer items and pantry staples are: the colours and the
+    /// alerts never bring those up, so they are easily forgotten
+    #[inline]
+    pub fn is_long_stored(&self) -> bool {
+        self.best_before.state() == FoodState::FarFromExpiring
+            && self.age().map_or(false, |age| {
+                age >= config::current().ageing.long_stored_days as u32
+            })
+    }
+}
+
+/// The fields of a [`Food`] to change, the other ones are left as they are
+#[derive(Default, Clone)]
+pub struct FoodPatch {
+    pub name: Option<String>,
+    pub best_before: Option<BestBefore>,
+    pub open: Option<bool>,
+    pub barcode: Option<Option<String>>,
+    pub category: Option<Option<String>>,
+    pub location: Option<Option<String>>,
+    pub nutrition: Option<Option<Nutrition>>,
+    pub price: Option<Option<u32>>,
+    pub store: Option<Option<String>>,
+}
+
+impl FoodPatch {
+    /// Change the fields of the [`Food`]
+    pub fn apply(self, food: &mut Food) {
+        if let Some(name) = self.name {
+            food.name = name;
+        }
+        if let Some(best_before) = self.best_before {
+            food.best_before = best_before;
+        }
+        if let Some(open) = self.open {
+            food.open = open;
+        }
+        if let Some(barcode) = self.barcode {
+            food.barcode = barcode;
+        }
+        if let Some(category) = self.category {
+            food.category = category;
+        }
+        if let Some(location) = self.location {
+            food.location = location;
+        }
+        if let Some(nutrition) = self.nutrition {
+            food.nutrition = nutrition;
+        }
+        if let Some(price) = self.price {
+            food.price = price;
+        }
+        if let Some(store) = self.store {
+            food.store = store;
+        }
+    }
+}
+
+/// A [`Fridge`] is just a collection of [`Food`]s, stored in the json file of an inventory.
+/// When sync is enabled, the synced event logs are the source of truth and the json file is
+/// only a local copy.
+#[derive(Deserialize, Serialize)]
+pub struct Fridge {
+    foods: Foods,
+
+    /// Path to the json file the [`Fridge`] has been opened from
+    #[serde(skip)]
+    path: String,
+
+    /// Where the changes are recorded when sync is enabled
+    #[serde(skip)]
+    replica: Option<Replica>,
+
+    /// The [`Food`]s as they were when the [`Fridge`] was opened, to find out what changed
+    #[serde(skip)]
+    opened: Foods,
+
+    /// Name of the inventory, for the [`Listener`]s
+    #[serde(skip)]
+    name: String,
+
+    /// What happened to the [`Food`]s since the last update, told to the [`Listener`]s
+    /// once saved
+    #[serde(skip)]
+    events: Vec<(FoodEvent, Food)>,
+}
+
+impl IntoIterator for Fridge {
+    type Item = Food;
+    type IntoIter = std::vec::IntoIter<Self::Item>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.foods.into_iter()
+    }
+}
+
+impl Fridge {
+    /// Open the [`Fridge`] of the inventory in use
+    #[inline]
+    pub fn open() -> Self {
+        let config = config::current();
+        Self::open_path(&config.inventory, config.inventory_path())
+    }
+
+    /// Open the [`Fridge`] of the given inventory, if it exists
+    #[inline]
+    pub fn open_inventory(name: &str) -> Option<Self> {
+        config::current()
+            .inventory_path_of(name)
+            .map(|path| Self::open_path(name, path))
+    }
+
+    /// Open the [`Fridge`] of an inventory. A missing json file is an empty [`Fridge`],
+    /// which is how new inventories start. If we get any other error in either reading the
+    /// json or deserializing, simply [`panic`] and log the error.
+    /// When sync is enabled, the [`Food`]s are merged from the synced folder instead, which
+    /// gets the local ones the first time this device joins it
+    fn open_path(name: &str, path: &str) -> Self {
+        READ_LEGACY_ID.with(|read| read.set(false));
+        let mut foods = match fs::OpenOptions::new().read(true).open(path) {
+            Ok(file) => {
+                let fridge: Self =
+                    serde_json::from_reader(file).unwrap_or_else(|err| log::fatal(err));
+                fridge.foods
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Foods::new(),
+            Err(err) => log::fatal(err),
+        };
+        let migrated = migrate_ids(&mut foods);
+
+        let replica = Replica::of_inventory(name);
+        if let Some(replica) = &replica {
+            replica.join(&foods);
+            foods = replica.merge().0;
+        }
+        let mut fridge = Self {
+            opened: foods.clone(),
+            foods,
+            path: path.to_string(),
+            replica,
+            name: name.to_string(),
+            events: Vec::new(),
+        };
+        if migrated {
+            fridge.write();
+        }
+        fridge
+    }
+
+    /// Update the [`Fridge`], overwriting the contents of the json file. When sync is
+    /// enabled, the changes made since the [`Fridge`] was opened are recorded first, and
+    /// the json file gets the merged [`Food`]s. The [`Food`]s eaten are then recorded in
+    /// the [`IntakeLog`], the ones thrown away in the [`WasteLog`], and the [`Listener`]s
+    /// are told about the changes
+    pub fn update(&mut self) {
+        if let Some(replica) = &self.replica {
+            replica.record(&self.opened, &self.foods);
+            self.foods = replica.merge().0;
+            self.opened = self.foods.clone();
+        }
+        self.write();
+
+        let events = mem::take(&mut self.events);
+        if events.is_empty() {
+            return;
+        }
+        let removed = |removal| {
+            events
+                .iter()
+                .filter(move |(event, _)| *event == removal)
+                .map(|(_, food)| food)
+                .peekable()
+        };
+        let mut eaten = removed(FoodEvent::Eaten);
+        if eaten.peek().is_some() {
+            eaten.fold(IntakeLog::open(), IntakeLog::record).update();
+        }
+        let mut discarded = removed(FoodEvent::Discarded);
+        if discarded.peek().is_some() {
+            discarded.fold(WasteLog::open(), WasteLog::record).update();
+        }
+
+        let listeners = LISTENERS
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        for (event, food) in &events {
+            for listener in &listeners {
+                listener(&self.name, *event, food);
+            }
+        }
+    }
+
+    /// Overwrite the contents of the json file
+    fn write(&mut self) {
+        self.foods.sort();
+        let contents = serde_json::to_string_pretty(self).unwrap_or_else(|err| log::fatal(err));
+        fs::write(&self.path, contents).unwrap_or_else(|err| log::fatal(err));
+    }
+
+    /// Add a [`Food`] to the [`Fridge`], giving it a new id unless it already has one
+    #[inline]
+    #[allow(clippy::should_implement_trait)]
+    pub fn add(self, food: Food) -> Self {
+        self.add_with_id(food).0
+    }
+
+    /// Like [`Fridge::add`], but also return the id the [`Food`] is stored with
+    pub fn add_with_id(mut self, mut food: Food) -> (Self, FoodId) {
+        if food.id.is_unassigned() {
+            food.id = FoodId::new();
+        }
+        let id = food.id;
+        self.events.push((FoodEvent::Added, food.clone()));
+        self.foods.push(food);
+        (self, id)
+    }
+
+    /// Iterate over the [`Food`]s of the [`Fridge`]
+    #[inline]
+    pub fn iter(&self) -> std::slice::Iter<'_, Food> {
+        self.foods.iter()
+    }
+
+    /// The synced folder the changes are recorded in, when sync is enabled
+    #[inline]
+    pub fn synced_dir(&self) -> Option<&std::path::Path> {
+        self.replica.as_ref().map(Replica::dir)
+    }
+
+    /// How many [`Food`]s are in the [`Fridge`]
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.foods.len()
+    }
+
+    /// Returns whether the [`Fridge`] is empty
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.foods.is_empty()
+    }
+
+    /// The [`Food`]s in the given [`FoodState`]
+    #[inline]
+    pub fn in_state(&self, state: FoodState) -> impl Iterator<Item = &Food> {
+        self.foods
+            .iter()
+            .filter(move |food| food.best_before.state() == state)
+    }
+
+    /// The [`Food`]s with the given name, ignoring the case
+    #[inline]
+    pub fn named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Food> {
+        self.foods
+            .iter()
+            .filter(move |food| food.name.eq_ignore_ascii_case(name.trim()))
+    }
+
+    /// Get the [`Food`] with the given id
+    #[inline]
+    pub fn get(&self, id: FoodId) -> Result<&Food, FridgeError> {
+        self.foods
+            .iter()
+            .find(|food| food.id == id)
+            .ok_or(FridgeError::NotFound(id))
+    }
+
+    /// Change some fields of the [`Food`] with the given id
+    pub fn patch(mut self, id: FoodId, patch: FoodPatch) -> Result<Self, FridgeError> {
+        patch.apply(self.get_mut(id)?);
+        Ok(self)
+    }
+
+    /// Open the [`Food`] with the given id, or close it back
+    pub fn set_open(mut self, id: FoodId, open: bool) -> Result<Self, FridgeError> {
+        let food = self.get_mut(id)?;
+        let opened = open && !food.open;
+        food.open = open;
+        if opened {
+            let food = food.clone();
+            self.events.push((FoodEvent::Opened, food));
+        }
+        Ok(self)
+    }
+
+    /// Remove the [`Food`] with the given id from the fridge, as eaten
+    #[inline]
+    pub fn eat(self, id: FoodId) -> Result<Self, FridgeError> {
+        self.remove(id, FoodEvent::Eaten)
+    }
+
+    /// Remove the [`Food`] with the given id from the fridge, as thrown away
+    #[inline]
+    pub fn discard(self, id: FoodId) -> Result<Self, FridgeError> {
+        self.remove(id, FoodEvent::Discarded)
+    }
+
+    /// Remove the [`Food`] with the given id from the fridge, as used up to cook the
+    /// [`Leftover`] with the id `leftover`. Unlike [`Fridge::eat`], no intake is recorded,
+    /// the leftover is what gets eaten
+    #[inline]
+    pub fn use_in(self, id: FoodId, leftover: FoodId) -> Result<Self, FridgeError> {
+        self.remove(id, FoodEvent::UsedIn(leftover))
+    }
+
+    /// Remove the [`Food`] with the given id from the fridge
+    fn remove(mut self, id: FoodId, event: FoodEvent) -> Result<Self, FridgeError> {
+        let index = self
+            .foods
+            .iter()
+            .position(|food| food.id == id)
+            .ok_or(FridgeError::NotFound(id))?;
+        let food = self.foods.remove(index);
+        self.events.push((event, food));
+        Ok(self)
+    }
+
+    /// Get the [`Food`] with the given id, to change it
+    #[inline]
+    fn get_mut(&mut self, id: FoodId) -> Result<&mut Food, FridgeError> {
+        self.foods
+            .iter_mut()
+            .find(|food| food.id == id)
+            .ok_or(FridgeError::NotFound(id))
+    }
+}
+
+/// Call a [`Listener`] with every change saved to any [`Fridge`] from now on
+pub fn on_change(listener: Listener) {
+    LISTENERS
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .push(listener);
+}
+
+/// Give a new [`FoodId`] to the [`Food`]s read without one or sharing the id of another
+/// one, which happened with the old numeric ids. Return whether the file needs to be
+/// rewritten, because of these new ids or because it still has numeric ids
+fn migrate_ids(foods: &mut Foods) -> bool {
+    let mut migrated = READ_LEGACY_ID.with(|read| read.replace(false));
+    let mut seen = HashSet::new();
+    for food in foods.iter_mut() {
+        if food.id.is_unassigned() || !seen.insert(food.id) {
+            food.id = FoodId::new();
+            seen.insert(food.id);
+            migrated = true;
+        }
+    }
+    migrated
+}
+
+/// Every [`Food`] of every inventory along with the name of its inventory, sorted by best
+/// before, so that what is expiring anywhere comes first
+pub fn all_foods() -> Vec<(String, Food)> {
+    let config = config::current();
+    let mut foods = config
+        .inventories
+        .keys()
+        .filter_map(|name| Some((name, Fridge::open_inventory(name)?)))
+        .flat_map(|(name, fridge)| fridge.into_iter().map(move |food| (name.clone(), food)))
+        .collect::<Vec<_>>();
+    foods.sort_by(|(_, a), (_, b)| a.cmp(b));
+    foods
+}
+
+/// Every long stored [`Food`] of every inventory along with the name of its inventory, the
+/// oldest first, see [`Food::is_long_stored`]
+pub fn long_stored_foods() -> Vec<(String, Food)> {
+    let mut foods = all_foods()
+        .into_iter()
+        .filter(|(_, food)| food.is_long_stored())
+        .collect::<Vec<_>>();
+    foods.sort_by_key(|(_, food)| food.added_on);
+    foods
+}
+
+/// Return a [`chrono::DateTime`] struct with fields updated at today, according to the
+/// [`Clock`](super::clock::Clock) in use
+#[inline]
+pub fn today() -> chrono::DateTime<chrono::Local> {
+    clock::now()
+}
+
+/// The current year, used for [`BestBefore`]s saved without one
+fn current_year() -> i32 {
+    today().year()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::Clock;
+    use crate::config::{Config, Logging};
+    use std::sync::Mutex;
+
+    /// The [`Clock`] is global, so the tests setting it take turns
+    static CLOCK: Mutex<()> = Mutex::new(());
+
+    /// Pretend to be the given day at noon, with the default settings rather than the
+    /// ones of a config file, and run a test
+    fn on_day(year: i32, month: u32, day: u32, test: impl FnOnce()) {
+        let _lock = CLOCK.lock().unwrap_or_else(PoisonError::into_inner);
+        config::apply(Config {
+            logging: Logging {
+                path: std::env::temp_dir()
+                    .join("food-fortress-tests.log")
+                    .display()
+                    .to_string(),
+                ..Logging::default()
+            },
+            ..Config::default()
+        });
+        let now = chrono::NaiveDate::from_ymd_opt(year, month, day)
+            .and_then(|date| date.and_hms_opt(12, 0, 0))
+            .unwrap();
+        clock::set(Clock::Fixed(now));
+        test();
+        clock::set(Clock::System);
+    }
+
+    fn parsed(text: &str) -> BestBefore {
+        BestBefore::parse(text).unwrap()
+    }
+
+    #[test]
+    fn days_left() {
+        on_day(2026, 3, 12, || {
+            assert_eq!(BestBefore::new(12, 3, 2026).days_left(), 0);
+            assert_eq!(BestBefore::new(13, 3, 2026).days_left(), 1);
+            assert_eq!(BestBefore::new(1, 4, 2026).days_left(), 20);
+            // Long gone is still no days left
+            assert_eq!(BestBefore::new(1, 1, 2020).days_left(), 0);
+        });
+    }
+
+    #[test]
+    fn state() {
+        on_day(2026, 3, 12, || {
+            assert_eq!(BestBefore::new(10, 3, 2026).state(), FoodState::Expired);
+            assert_eq!(BestBefore::new(12, 3, 2026).state(), FoodState::Expired);
+            assert_eq!(
+                BestBefore::new(13, 3, 2026).state(),
+                FoodState::CloseFromExpiring
+            );
+            assert_eq!(
+                BestBefore::new(15, 3, 2026).state(),
+                FoodState::CloseFromExpiring
+            );
+            assert_eq!(
+                BestBefore::new(16, 3, 2026).state(),
+                FoodState::FarFromExpiring
+            );
+        });
+    }
+
+    #[test]
+    fn parse_formats() {
+        on_day(2026, 3, 12, || {
+            assert!(parsed("20/03") == BestBefore::new(20, 3, 2026));
+            assert!(parsed(" 20/03/2027 ") == BestBefore::new(20, 3, 2027));
+            assert!(parsed("2026-04-01") == BestBefore::new(1, 4, 2026));
+            assert!(parsed("+5") == BestBefore::new(17, 3, 2026));
+            assert!(parsed("+5d") == BestBefore::new(17, 3, 2026));
+            assert!(parsed("+2w") == BestBefore::new(26, 3, 2026));
+            assert!(parsed("+1m") == BestBefore::new(12, 4, 2026));
+        });
+    }
+
+    #[test]
+    fn parse_errors() {
+        on_day(2026, 3, 12, || {
+            assert!(matches!(
+                BestBefore::parse("tomorrow"),
+                Err(DateError::UnknownFormat)
+            ));
+            assert!(matches!(
+                BestBefore::parse("12/13"),
+                Err(DateError::InvalidMonth(13))
+            ));
+            assert!(matches!(
+                BestBefore::parse("30/02"),
+                Err(DateError::InvalidDay {
+                    day: 30,
+                    month: 2,
+                    year: 2026
+                })
+            ));
+            assert!(matches!(
+                BestBefore::parse("+5y"),
+                Err(DateError::InvalidOffset)
+            ));
+        });
+    }
+
+    #[test]
+    fn parse_rolls_over_to_next_year() {
+        on_day(2026, 12, 30, || {
+            // Early January is next year, while a date of last month is still this year
+            assert!(parsed("05/01") == BestBefore::new(5, 1, 2027));
+            assert!(parsed("10/12") == BestBefore::new(10, 12, 2026));
+            assert!(parsed("+5d") == BestBefore::new(4, 1, 2027));
+        });
+        on_day(2026, 3, 12, || {
+            // Within the tolerance, a past date stays this year
+            assert!(parsed("10/02") == BestBefore::new(10, 2, 2026));
+            assert!(parsed("01/02") == BestBefore::new(1, 2, 2027));
+        });
+    }
+
+    #[test]
+    fn fixed_clock_dates() {
+        on_day(2024, 2, 28, || {
+            assert!(BestBefore::today() == BestBefore::new(28, 2, 2024));
+            assert!(BestBefore::in_days(1) == BestBefore::new(29, 2, 2024));
+            assert!(BestBefore::in_days(2) == BestBefore::new(1, 3, 2024));
+            assert!(BestBefore::in_months(12) == BestBefore::new(28, 2, 2025));
+        });
+    }
+}
@@ -0,0 +1,79 @@
+//! The clock mod tells the date and time to the rest of the app. Everything that depends on
+//! today, from the states of the foods to the log timestamps, reads the [`Clock`] in use
+//! rather than the system clock, so that it can be pretended to be another day, either to
+//! check the expiry logic or with `--pretend-date`.
+
+use chrono::{NaiveDate, TimeZone};
+use std::sync::{PoisonError, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The [`Clock`] in use, the system clock unless another one has been set
+static CURRENT: RwLock<Clock> = RwLock::new(Clock::System);
+
+/// Where the current date and time come from
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Clock {
+    /// The date and time of the system
+    System,
+
+    /// Always the same date and time
+    Fixed(chrono::NaiveDateTime),
+
+    /// The system clock moved by some duration, so that time still flows
+    Offset(chrono::Duration),
+}
+
+impl Clock {
+    /// The system clock moved so that it is now the given date and time, from which time
+    /// flows as usual, rolling over at midnight
+    #[inline]
+    pub fn pretending(now: chrono::NaiveDateTime) -> Self {
+        Self::Offset(now - chrono::Local::now().naive_local())
+    }
+
+    /// The current date and time of the [`Clock`]
+    pub fn now(&self) -> chrono::DateTime<chrono::Local> {
+        match self {
+            Self::System => chrono::Local::now(),
+            Self::Fixed(now) => chrono::Local
+                .from_local_datetime(now)
+                .earliest()
+                .unwrap_or_else(chrono::Local::now),
+            Self::Offset(offset) => chrono::Local::now() + *offset,
+        }
+    }
+}
+
+/// The current date and time of the [`Clock`] in use
+#[inline]
+pub fn now() -> chrono::DateTime<chrono::Local> {
+    current().now()
+}
+
+/// The current date of the [`Clock`] in use
+#[inline]
+pub fn today() -> NaiveDate {
+    now().naive_local().date()
+}
+
+/// Milliseconds since the Unix epoch according to the system clock, whatever the [`Clock`]
+/// in use. Meant for the deadlines and the timestamps compared with other processes or
+/// devices, which must not move with a pretended date
+#[inline]
+pub fn system_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as i64)
+}
+
+/// The [`Clock`] in use
+#[inline]
+pub fn current() -> Clock {
+    *CURRENT.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Use another [`Clock`] from now on
+#[inline]
+pub fn set(clock: Clock) {
+    *CURRENT.write().unwrap_or_else(PoisonError::into_inner) = clock;
+}
@@ -0,0 +1,769 @@
+//! The config mod holds the user settings, persisted in a toml file. The settings are
+//! loaded once and kept in memory, so that changes made in the settings window are
+//! applied live, without restarting the app.
+
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::sync::{Arc, PoisonError, RwLock};
+
+use super::log;
+
+/// Path to the toml config file
+pub const CONFIG: &str = "config.toml";
+
+/// Name and path of the inventory available out of the box
+const DEFAULT_INVENTORY: (&str, &str) = ("Home", "json/fridge.json");
+
+/// The [`Config`] currently in use, loaded the first time it is needed
+static CURRENT: RwLock<Option<Arc<Config>>> = RwLock::new(None);
+
+/// The settings of the app
+#[derive(Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct Config {
+    /// How best before dates are displayed, see [`chrono::format::strftime`]
+    pub date_format: String,
+
+    /// Name of the inventory in use
+    pub inventory: String,
+
+    /// Every inventory, such as the home fridge or the office one, from its name to the
+    /// path of its json file
+    pub inventories: BTreeMap<String, String>,
+
+    /// Symbol or code the prices are shown with, such as `€` or `USD`
+    pub currency: String,
+
+    pub appearance: Appearance,
+    pub expiry: Expiry,
+    pub alerts: Alerts,
+    pub notifications: Notifications,
+    pub digest: Digest,
+    pub webhooks: Webhooks,
+    pub mqtt: Mqtt,
+    pub sound: Sound,
+    pub add_food: AddFood,
+    pub leftovers: Leftovers,
+    pub ageing: Ageing,
+    pub paths: Paths,
+    pub sync: Sync,
+    pub window: Window,
+    pub logging: Logging,
+}
+
+/// Font sizes, sizes of the table and colours of the [`FoodState`](super::backend::FoodState)s
+#[derive(Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct Appearance {
+    pub font_size: f32,
+    pub header_font_size: f32,
+    pub menu_font_size: f32,
+    pub row_height: f32,
+    pub best_before_column_width: f32,
+    pub age_column_width: f32,
+    pub button_column_width: f32,
+    pub far_from_expiring_color: [u8; 3],
+    pub close_from_expiring_color: [u8; 3],
+    pub expired_color: [u8; 3],
+}
+
+/// Thresholds of the [`FoodState`](super::backend::FoodState)s, in days left
+#[derive(Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct Expiry {
+    /// Foods with this many days left or less are expired
+    pub expired_days: u16,
+
+    /// Foods with this many days left or less, but not expired, are close from expiring
+    pub close_days: u16,
+}
+
+/// Alerts about expired and expiring foods, see [`alerts`](super::alerts)
+#[derive(Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct Alerts {
+    pub enabled: bool,
+
+    /// How often to check for foods to alert about, besides at startup
+    pub interval_minutes: u32,
+
+    /// How long the snooze button silences the alerts
+    pub snooze_minutes: u32,
+}
+
+/// Desktop notifications about expiring foods
+#[derive(Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct Notifications {
+    pub enabled: bool,
+
+    /// Notify when foods become close from expiring
+    pub expiring: bool,
+
+    /// Notify when foods expire
+    pub expired: bool,
+
+    /// How often the daemon checks for foods to notify about, and whether the
+    /// [`Digest`] is due
+    pub interval_minutes: u32,
+}
+
+/// Email digest of the expired and expiring foods
+#[derive(Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct Digest {
+    pub enabled: bool,
+    pub frequency: DigestFrequency,
+
+    /// Day the weekly digest is sent on
+    pub weekday: chrono::Weekday,
+
+    /// Hour the digest is sent at, from 0 to 23
+    pub hour: u32,
+
+    /// Sender, such as `fridge@example.com` or `Food Fortress <fridge@example.com>`
+    pub from: String,
+
+    /// Household members the digest is sent to
+    pub recipients: Vec<String>,
+
+    pub smtp: Smtp,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DigestFrequency {
+    Daily,
+    Weekly,
+}
+
+/// SMTP server the [`Digest`] is sent through
+#[derive(Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct Smtp {
+    pub host: String,
+    pub port: u16,
+    pub security: SmtpSecurity,
+
+    /// No authentication when empty
+    pub username: String,
+
+    /// Overridden by the `FOOD_FORTRESS_SMTP_PASSWORD` environment variable
+    pub password: String,
+}
+
+/// How the connection to the SMTP server is encrypted
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpSecurity {
+    /// Plain text, only for a local server or sink
+    None,
+    StartTls,
+    Tls,
+}
+
+/// Outgoing webhooks for home automation
+#[derive(Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct Webhooks {
+    pub enabled: bool,
+
+    /// How many times a payload is sent before giving up
+    pub max_attempts: u32,
+
+    /// Wait before the first retry, doubled at each retry
+    pub retry_after_seconds: u32,
+
+    pub hooks: Vec<Webhook>,
+}
+
+/// A URL the [`WebhookEvent`]s are POSTed to
+#[derive(Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct Webhook {
+    pub url: String,
+
+    /// Key of the HMAC signature of the payloads, not signed when empty
+    pub secret: String,
+
+    pub events: Vec<WebhookEvent>,
+}
+
+/// MQTT broker the daemon publishes the inventories to
+#[derive(Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct Mqtt {
+    pub enabled: bool,
+    pub host: String,
+    pub port: u16,
+
+    /// No authentication when empty
+    pub username: String,
+    pub password: String,
+
+    /// Topics of the app start with this
+    pub topic_prefix: String,
+
+    /// Where Home Assistant looks for discovery configs
+    pub discovery_prefix: String,
+
+    /// Publish a sensor per food, besides the counts per state
+    pub publish_foods: bool,
+}
+
+/// What happened to a food, each [`Webhook`] choosing the ones it is sent
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookEvent {
+    Added,
+    Opened,
+    Eaten,
+    Discarded,
+
+    /// The food became close from expiring
+    Expiring,
+
+    /// The food expired
+    Expired,
+}
+
+impl WebhookEvent {
+    /// Every [`WebhookEvent`], in the order they are shown in the settings
+    pub const ALL: [Self; 6] = [
+        Self::Added,
+        Self::Opened,
+        Self::Eaten,
+        Self::Discarded,
+        Self::Expiring,
+        Self::Expired,
+    ];
+}
+
+/// Sound effects. An empty path plays no sound
+#[derive(Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct Sound {
+    /// false when the sounds are muted
+    pub enabled: bool,
+
+    /// From 0 to 1
+    pub volume: f32,
+
+    pub added: String,
+    pub opened: String,
+
+    /// Sound played when a food has been completely eaten
+    #[serde(alias = "eating_sound")]
+    pub eaten: String,
+
+    pub discarded: String,
+
+    /// Sound played at startup when foods are expiring
+    pub expiry_alert: String,
+}
+
+#[derive(Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct AddFood {
+    /// How many copies of a food can be added at once
+    pub max_quantity: u8,
+}
+
+/// Shelf life of the leftovers, which have no printed best before date
+#[derive(Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct Leftovers {
+    /// Days the dishes without a rule keep
+    pub default_days: u16,
+
+    /// Days each type of dish keeps once cooked, such as 1 for cooked rice
+    pub dishes: BTreeMap<String, u16>,
+}
+
+/// Foods stored for long, see [`Food::is_long_stored`](super::backend::Food::is_long_stored)
+#[derive(Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct Ageing {
+    /// Foods far from expiring are long stored once added this many days ago
+    pub long_stored_days: u16,
+}
+
+/// Paths to the data files, except for the inventories
+#[derive(Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct Paths {
+    pub products: String,
+    pub known_foods: String,
+    pub alerts: String,
+    pub notifications: String,
+    pub digest: String,
+    pub webhooks: String,
+    pub intake: String,
+    pub waste: String,
+}
+
+/// Sharing the inventories with other devices through a synced folder, see
+/// [`sync`](super::sync)
+#[derive(Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct Sync {
+    pub enabled: bool,
+
+    /// Folder synced between the devices, such as a Dropbox or Syncthing one
+    pub folder: String,
+
+    /// Name of this device in the synced folder, generated the first time the app starts
+    pub device: String,
+
+    /// Changes older than this many days are compacted into a snapshot
+    pub compact_after_days: u16,
+}
+
+/// Initial size of the window
+#[derive(Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct Window {
+    pub width: f32,
+    pub height: f32,
+}
+
+/// The log file, see [`log`](super::log)
+#[derive(Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct Logging {
+    /// Messages below this level are not logged
+    pub level: log::Level,
+
+    /// One json object per line instead of plain text, for log collectors
+    pub json: bool,
+
+    /// Created along with its folder when missing
+    pub path: String,
+
+    pub rotation: LogRotation,
+
+    /// With [`LogRotation::Size`], the log file is rotated before it grows past this size
+    pub max_size_kb: u64,
+
+    /// How many rotated log files are kept, the older ones are deleted
+    pub retention: u32,
+}
+
+/// When the log file is set aside for a new one
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogRotation {
+    Never,
+    Size,
+    Daily,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            date_format: "%d / %m".to_string(),
+            inventory: DEFAULT_INVENTORY.0.to_string(),
+            inventories: BTreeMap::from([(
+                DEFAULT_INVENTORY.0.to_string(),
+                DEFAULT_INVENTORY.1.to_string(),
+            )]),
+            currency: "€".to_string(),
+            appearance: Appearance::default(),
+            expiry: Expiry::default(),
+            alerts: Alerts::default(),
+            notifications: Notifications::default(),
+            digest: Digest::default(),
+            webhooks: Webhooks::default(),
+            mqtt: Mqtt::default(),
+            sound: Sound::default(),
+            add_food: AddFood::default(),
+            leftovers: Leftovers::default(),
+            ageing: Ageing::default(),
+            paths: Paths::default(),
+            sync: Sync::default(),
+            window: Window::default(),
+            logging: Logging::default(),
+        }
+    }
+}
+
+impl Default for Appearance {
+    fn default() -> Self {
+        Self {
+            font_size: 23.0,
+            header_font_size: 32.0,
+            menu_font_size: 18.0,
+            row_height: 26.0,
+            best_before_column_width: 200.0,
+            age_column_width: 110.0,
+            button_column_width: 137.0,
+            far_from_expiring_color: [0, 255, 0],
+            close_from_expiring_color: [255, 255, 0],
+            expired_color: [255, 0, 0],
+        }
+    }
+}
+
+impl Default for Expiry {
+    fn default() -> Self {
+        Self {
+            expired_days: 0,
+            close_days: 3,
+        }
+    }
+}
+
+impl Default for Alerts {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_minutes: 60,
+            snooze_minutes: 240,
+        }
+    }
+}
+
+impl Default for Notifications {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            expiring: true,
+            expired: true,
+            interval_minutes: 30,
+        }
+    }
+}
+
+impl Default for Digest {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            frequency: DigestFrequency::Daily,
+            weekday: chrono::Weekday::Sun,
+            hour: 8,
+            from: String::new(),
+            recipients: Vec::new(),
+            smtp: Smtp::default(),
+        }
+    }
+}
+
+impl Default for Smtp {
+    fn default() -> Self {
+        Self {
+            host: String::new(),
+            port: 587,
+            security: SmtpSecurity::StartTls,
+            username: String::new(),
+            password: String::new(),
+        }
+    }
+}
+
+impl Default for Webhooks {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_attempts: 10,
+            retry_after_seconds: 30,
+            hooks: Vec::new(),
+        }
+    }
+}
+
+impl Default for Webhook {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            secret: String::new(),
+            events: WebhookEvent::ALL.to_vec(),
+        }
+    }
+}
+
+impl Default for Mqtt {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            host: "localhost".to_string(),
+            port: 1883,
+            username: String::new(),
+            password: String::new(),
+            topic_prefix: "food-fortress".to_string(),
+            discovery_prefix: "homeassistant".to_string(),
+            publish_foods: true,
+        }
+    }
+}
+
+impl Default for Sound {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            volume: 1.0,
+            added: String::new(),
+            opened: String::new(),
+            eaten: "sounds/minecraft_eating_sound.mp3".to_string(),
+            discarded: String::new(),
+            expiry_alert: String::new(),
+        }
+    }
+}
+
+impl Default for AddFood {
+    fn default() -> Self {
+        Self { max_quantity: 10 }
+    }
+}
+
+impl Default for Leftovers {
+    fn default() -> Self {
+        Self {
+            default_days: 3,
+            dishes: [
+                ("Cooked rice", 1),
+                ("Fish", 2),
+                ("Cooked meat", 3),
+                ("Pasta", 3),
+                ("Pizza", 3),
+                ("Soup", 3),
+                ("Stew", 4),
+            ]
+            .into_iter()
+            .map(|(dish, days)| (dish.to_string(), days))
+            .collect(),
+        }
+    }
+}
+
+impl Leftovers {
+    /// Days a type of dish keeps once cooked, ignoring case
+    pub fn shelf_life(&self, dish: &str) -> u16 {
+        self.dishes
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(dish.trim()))
+            .map_or(self.default_days, |(_, days)| *days)
+    }
+}
+
+impl Default for Ageing {
+    fn default() -> Self {
+        Self {
+            long_stored_days: 90,
+        }
+    }
+}
+
+impl Default for Paths {
+    fn default() -> Self {
+        Self {
+            products: "json/products.json".to_string(),
+            known_foods: "json/known_foods.json".to_string(),
+            alerts: "json/alerts.json".to_string(),
+            notifications: "json/notifications.json".to_string(),
+            digest: "json/digest.json".to_string(),
+            webhooks: "json/webhooks.json".to_string(),
+            intake: "json/intake.json".to_string(),
+            waste: "json/waste.json".to_string(),
+        }
+    }
+}
+
+impl Default for Sync {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            folder: String::new(),
+            device: String::new(),
+            compact_after_days: 7,
+        }
+    }
+}
+
+impl Default for Window {
+    fn default() -> Self {
+        Self {
+            width: 660.0,
+            height: 550.0,
+        }
+    }
+}
+
+impl Default for Logging {
+    fn default() -> Self {
+        Self {
+            level: log::Level::Info,
+            json: false,
+            path: "log/log.log".to_string(),
+            rotation: LogRotation::Size,
+            max_size_kb: 1024,
+            retention: 5,
+        }
+    }
+}
+
+impl Config {
+    /// Path to the json file of the inventory in use
+    #[inline]
+    pub fn inventory_path(&self) -> &str {
+        self.inventory_path_of(&self.inventory)
+            .unwrap_or(DEFAULT_INVENTORY.1)
+    }
+
+    /// Path to the json file of the given inventory, if it exists
+    #[inline]
+    pub fn inventory_path_of(&self, name: &str) -> Option<&str> {
+        self.inventories.get(name).map(String::as_str)
+    }
+
+    /// The [`Config`] without its passwords and secrets, so that it can be shared
+    pub fn redacted(&self) -> Self {
+        let redact = |secret: &mut String| {
+            if !secret.is_empty() {
+                *secret = "<redacted>".to_string();
+            }
+        };
+        let mut config = self.clone();
+        redact(&mut config.digest.smtp.password);
+        redact(&mut config.mqtt.password);
+        for hook in &mut config.webhooks.hooks {
+            redact(&mut hook.secret);
+        }
+        config
+    }
+
+    /// Load the [`Config`] from the config file. A missing file is created with the default
+    /// settings. An unreadable or invalid file is logged and the defaults are used instead,
+    /// because a typo in the settings should not prevent the app from starting
+    fn load() -> Self {
+        let contents = match fs::read_to_string(CONFIG) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                let mut config = Self::default();
+                config.sync.device = new_device_name();
+                config.save();
+                return config;
+            }
+            Err(err) => {
+                log::warning(format!(
+                    "Config file '{}' could not be read, using the defaults: {}",
+                    CONFIG, err
+                ));
+                return Self::default();
+            }
+        };
+
+        match toml::from_str::<Self>(&contents) {
+            Ok(mut config) => {
+                if !is_valid_date_format(&config.date_format) {
+                    log::warning(format!(
+                        "Date format '{}' is not valid, using the default one",
+                        config.date_format
+                    ));
+                    config.date_format = Self::default().date_format;
+                }
+                if config.inventories.is_empty() {
+                    config.inventories = Self::default().inventories;
+                }
+                if !config.inventories.contains_key(&config.inventory) {
+                    log::warning(format!(
+                        "Inventory '{}' does not exist, using another one",
+                        config.inventory
+                    ));
+                    config.inventory = config.inventories.keys().next().unwrap().clone();
+                    // Guarded by emptiness check
+                }
+                if config.sync.device.trim().is_empty() {
+                    config.sync.device = new_device_name();
+                    config.save();
+                }
+                config
+            }
+            Err(err) => {
+                log::warning(format!(
+                    "Config file '{}' could not be parsed, using the defaults: {}",
+                    CONFIG, err
+                ));
+                Self::default()
+            }
+        }
+    }
+
+    /// Write the [`Config`] to the config file. If we can't, just log the error: the
+    /// settings still apply until the app is closed
+    fn save(&self) {
+        let result = toml::to_string_pretty(self)
+            .map_err(|err| err.to_string())
+            .and_then(|contents| fs::write(CONFIG, contents).map_err(|err| err.to_string()));
+        if let Err(err) = result {
+            log::warning(format!(
+                "Config file '{}' could not be written: {}",
+                CONFIG, err
+            ));
+        }
+    }
+}
+
+/// Get the [`Config`] currently in use
+pub fn current() -> Arc<Config> {
+    if let Some(config) = CURRENT
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .as_ref()
+    {
+        return Arc::clone(config);
+    }
+
+    let config = Arc::new(Config::load());
+    log::configure(config.logging.clone());
+    *CURRENT.write().unwrap_or_else(PoisonError::into_inner) = Some(Arc::clone(&config));
+    config
+}
+
+/// Use the given [`Config`] from now on, without persisting it
+pub fn apply(config: Config) {
+    log::configure(config.logging.clone());
+    *CURRENT.write().unwrap_or_else(PoisonError::into_inner) = Some(Arc::new(config));
+}
+
+/// Use the given [`Config`] from now on, and persist it in the config file
+pub fn save(config: Config) {
+    config.save();
+    apply(config);
+}
+
+/// Returns whether the date format can be used to display dates
+pub fn is_valid_date_format(format: &str) -> bool {
+    !format.is_empty()
+        && chrono::format::StrftimeItems::new(format)
+            .all(|item| !matches!(item, chrono::format::Item::Error))
+}
+
+/// A name for this device, unique among the devices syncing the same folder: the host
+/// name followed by a few characters derived from the current time and process
+fn new_device_name() -> String {
+    let host = std::env::var("COMPUTERNAME")
+        .or_else(|_| std::env::var("HOSTNAME"))
+        .unwrap_or_else(|_| "device".to_string());
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos());
+    format!(
+        "{}-{:06x}",
+        host,
+        (nanos ^ std::process::id() as u128) & 0xff_ffff
+    )
+}
+
+/// Path to the json file of a new inventory, derived from its name
+pub fn new_inventory_path(name: &str) -> String {
+    let file_name = name
+        .trim()
+        .to_lowercase()
+        .chars()
+        .map(|ch| if ch.is_ascii_alphanumeric() { ch } else { '_' })
+        .collect::<String>();
+    format!("json/{}.json", file_name)
+}
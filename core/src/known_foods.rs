@@ -0,0 +1,140 @@
+//! The known_foods mod remembers every food the user has added, so that the add food menu
+//! of the GUI can autocomplete names and prefill the fields with the learned defaults of a
+//! food.
+
+use serde_derive::{Deserialize, Serialize};
+use std::cmp;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+
+use super::config;
+use super::log;
+
+/// How many names are suggested at most
+const MAX_SUGGESTIONS: usize = 5;
+
+/// What we learned about a food name from the previous times it was added
+#[derive(Deserialize, Serialize, Clone)]
+pub struct KnownFood {
+    /// Name as the user typed it the last time
+    pub name: String,
+    pub category: Option<String>,
+    pub location: Option<String>,
+
+    /// How many copies are usually added at once
+    pub quantity: u8,
+
+    /// Average number of days between the day the food is added and its best before
+    pub shelf_life: u16,
+
+    /// How many times the food has been added
+    pub count: u32,
+}
+
+/// The [`KnownFoods`] maps lowercase food names to [`KnownFood`]s
+#[derive(Deserialize, Serialize, Default)]
+pub struct KnownFoods {
+    foods: BTreeMap<String, KnownFood>,
+}
+
+impl KnownFoods {
+    /// Open the [`KnownFoods`]. A missing file simply means nothing has been learned yet,
+    /// while any other error is logged and the process aborts
+    pub fn open() -> Self {
+        let file = match fs::File::open(&config::current().paths.known_foods) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Self::default(),
+            Err(err) => log::fatal(err),
+        };
+        serde_json::from_reader(file).unwrap_or_else(|err| log::fatal(err))
+    }
+
+    /// How many [`KnownFood`]s there are
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.foods.len()
+    }
+
+    /// Returns whether there are no [`KnownFood`]s
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.foods.is_empty()
+    }
+
+    /// Update the [`KnownFoods`], overwriting the contents of the json file
+    pub fn update(&self) {
+        let contents = serde_json::to_string_pretty(self).unwrap_or_else(|err| log::fatal(err));
+        fs::write(&config::current().paths.known_foods, contents)
+            .unwrap_or_else(|err| log::fatal(err));
+    }
+
+    /// Get what we know about a food name, ignoring case
+    #[inline]
+    pub fn get(&self, name: &str) -> Option<&KnownFood> {
+        self.foods.get(&name.trim().to_lowercase())
+    }
+
+    /// Learn from a food that has just been added. Category, location and quantity are the
+    /// latest ones, while the shelf life is averaged over all the times the food was added
+    pub fn learn(
+        mut self,
+        name: &str,
+        category: Option<String>,
+        location: Option<String>,
+        quantity: u8,
+        shelf_life: u16,
+    ) -> Self {
+        let key = name.trim().to_lowercase();
+        let (average, count) = match self.foods.get(&key) {
+            Some(known) => {
+                let total = known.shelf_life as u32 * known.count + shelf_life as u32;
+                (total / (known.count + 1), known.count + 1)
+            }
+            None => (shelf_life as u32, 1),
+        };
+        self.foods.insert(
+            key,
+            KnownFood {
+                name: name.trim().to_string(),
+                category,
+                location,
+                quantity,
+                shelf_life: average as u16,
+                count,
+            },
+        );
+        self
+    }
+
+    /// Complete the given prefix, ignoring case. Known foods come first, the most frequently
+    /// added on top, followed by `others`: names not learned yet, such as the ones of the
+    /// foods currently in the fridge
+    pub fn complete(&self, prefix: &str, others: impl IntoIterator<Item = String>) -> Vec<String> {
+        let prefix = prefix.trim().to_lowercase();
+        if prefix.is_empty() {
+            return Vec::new();
+        }
+
+        let mut known = self
+            .foods
+            .iter()
+            .filter(|(key, _)| key.starts_with(&prefix))
+            .map(|(_, food)| food)
+            .collect::<Vec<_>>();
+        known.sort_by_key(|food| cmp::Reverse(food.count));
+
+        let mut names = known
+            .into_iter()
+            .map(|food| food.name.clone())
+            .collect::<Vec<_>>();
+        for name in others {
+            let key = name.to_lowercase();
+            if key.starts_with(&prefix) && !names.iter().any(|n| n.to_lowercase() == key) {
+                names.push(name);
+            }
+        }
+        names.truncate(MAX_SUGGESTIONS);
+        names
+    }
+}
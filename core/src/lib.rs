@@ -0,0 +1,22 @@
+//! The core of Food Fortress: the foods, the inventories they are stored in and the
+//! settings, without any GUI or audio, so that other tools can read and change the same
+//! inventories as the app.
+//!
+//! ```no_run
+//! use food_fortress_core::backend::{BestBefore, Food, FoodState, Fridge};
+//!
+//! let mut fridge = Fridge::open().add(Food::new("Milk".to_string(), BestBefore::in_days(5)));
+//! fridge.update();
+//! println!("{} foods expired", fridge.in_state(FoodState::Expired).count());
+//! ```
+
+pub mod alerts;
+pub mod backend;
+pub mod clock;
+pub mod config;
+pub mod known_foods;
+pub mod log;
+pub mod nutrition;
+pub mod products;
+pub mod sync;
+pub mod waste;
@@ -0,0 +1,417 @@
+//! Little logging library. Messages at or above the minimum [`Level`] of the settings are
+//! appended to the log file, as plain text or as json lines, and the file is rotated by
+//! size or by date. If the log file cannot be written, messages go to stderr instead,
+//! because we have nowhere else to log to.
+
+use serde_derive::{Deserialize, Serialize};
+use std::ffi::OsString;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError, RwLock};
+
+use super::clock;
+use super::config::{LogRotation, Logging};
+
+/// Log datetime format
+const DATETIME_LOG_FORMAT: &str = "%Y-%m-%d %H:%M:%S:%3f";
+
+/// The [`Logging`] settings in use, the defaults until [`configure`] is called
+static SETTINGS: RwLock<Option<Logging>> = RwLock::new(None);
+
+/// How much of the end of the log file [`tail`] reads at most
+const TAIL_BYTES: u64 = 512 * 1024;
+
+/// Date of the last message written, to rotate the log file daily. Also keeps the threads
+/// from writing at the same time
+static LAST_WRITTEN: Mutex<Option<chrono::NaiveDate>> = Mutex::new(None);
+
+/// Log level, from the least to the most severe
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl Level {
+    /// Every level, from the least to the most severe
+    pub const ALL: [Self; 4] = [Self::Debug, Self::Info, Self::Warn, Self::Error];
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// A line of the log file, as read back by [`tail`]
+#[derive(Clone)]
+pub struct Entry {
+    /// Empty for the lines that are not a message, such as the rest of a message on
+    /// several lines
+    pub timestamp: String,
+
+    /// [`None`] for the lines that are not a message
+    pub level: Option<Level>,
+
+    pub message: String,
+}
+
+impl Entry {
+    /// Parse a line of the log file, written as plain text or as json
+    pub fn parse(line: &str) -> Self {
+        #[derive(Deserialize)]
+        struct Json {
+            timestamp: String,
+            level: Level,
+            message: String,
+        }
+
+        if let Ok(json) = serde_json::from_str::<Json>(line) {
+            return Self {
+                timestamp: json.timestamp,
+                level: Some(json.level),
+                message: json.message,
+            };
+        }
+        // `<date> <time> <level>: <message>`, where older logs say `Warning`
+        let mut parts = line.splitn(3, ' ');
+        if let (Some(date), Some(time), Some(rest)) = (parts.next(), parts.next(), parts.next()) {
+            if let Some((level, message)) = rest.split_once(": ") {
+                let level = match level {
+                    "Debug" => Some(Level::Debug),
+                    "Info" => Some(Level::Info),
+                    "Warn" | "Warning" => Some(Level::Warn),
+                    "Error" => Some(Level::Error),
+                    _ => None,
+                };
+                if level.is_some() {
+                    return Self {
+                        timestamp: format!("{} {}", date, time),
+                        level,
+                        message: message.to_string(),
+                    };
+                }
+            }
+        }
+        Self {
+            timestamp: String::new(),
+            level: None,
+            message: line.to_string(),
+        }
+    }
+}
+
+/// Use the given [`Logging`] settings from now on
+pub fn configure(settings: Logging) {
+    *SETTINGS.write().unwrap_or_else(PoisonError::into_inner) = Some(settings);
+}
+
+/// Log a message, unless its level is below the minimum of the settings
+pub fn log(level: Level, msg: impl fmt::Display) {
+    let settings = SETTINGS
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+        .unwrap_or_default();
+    if level < settings.level {
+        return;
+    }
+
+    let now = clock::now();
+    let line = if settings.json {
+        serde_json::json!({
+            "timestamp": now.to_rfc3339(),
+            "level": level,
+            "message": msg.to_string(),
+        })
+        .to_string()
+    } else {
+        format!("{} {}: {}", now.format(DATETIME_LOG_FORMAT), level, msg)
+    };
+
+    let mut last_written = LAST_WRITTEN.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Err(err) = write(&settings, &line, clock::today(), &mut last_written) {
+        eprintln!("Log file '{}' cannot be written: {}", settings.path, err);
+        eprintln!("{}", line);
+    }
+}
+
+/// Path to the log file in use
+pub fn path() -> String {
+    SETTINGS
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .as_ref()
+        .map_or_else(|| Logging::default().path, |settings| settings.path.clone())
+}
+
+/// The last `lines` lines of the log file, oldest first. A missing log file has no lines
+pub fn tail(lines: usize) -> io::Result<Vec<String>> {
+    let mut file = match fs::File::open(path()) {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+    let start = file.metadata()?.len().saturating_sub(TAIL_BYTES);
+    file.seek(io::SeekFrom::Start(start))?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+
+    let text = String::from_utf8_lossy(&bytes);
+    let mut all = text.lines().collect::<Vec<_>>();
+    if start > 0 && !all.is_empty() {
+        // The first line has been cut
+        all.remove(0);
+    }
+    let skipped = all.len().saturating_sub(lines);
+    Ok(all[skipped..].iter().map(|line| line.to_string()).collect())
+}
+
+/// Append a line to the log file, creating it along with its folder if needed, after
+/// rotating it if it is due
+fn write(
+    settings: &Logging,
+    line: &str,
+    today: chrono::NaiveDate,
+    last_written: &mut Option<chrono::NaiveDate>,
+) -> io::Result<()> {
+    let path = Path::new(&settings.path);
+    if let Some(folder) = path.parent() {
+        fs::create_dir_all(folder)?;
+    }
+    if is_rotation_due(settings, path, line.len() as u64 + 1, today, *last_written)? {
+        rotate(path, settings.retention)?;
+    }
+    *last_written = Some(today);
+
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    writeln!(file, "{}", line)
+}
+
+/// Returns whether the log file must be rotated before writing `len` more bytes to it
+fn is_rotation_due(
+    settings: &Logging,
+    path: &Path,
+    len: u64,
+    today: chrono::NaiveDate,
+    last_written: Option<chrono::NaiveDate>,
+) -> io::Result<bool> {
+    let metadata = match fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(err) => return Err(err),
+    };
+    if metadata.len() == 0 {
+        return Ok(false);
+    }
+    Ok(match settings.rotation {
+        LogRotation::Never => false,
+        LogRotation::Size => metadata.len() + len > settings.max_size_kb.max(1) * 1024,
+        LogRotation::Daily => {
+            // The first message of the session goes by when the file was last written
+            let last_written = last_written.or_else(|| {
+                let modified = chrono::DateTime::<chrono::Local>::from(metadata.modified().ok()?);
+                Some(modified.naive_local().date())
+            });
+            last_written.map_or(false, |date| date < today)
+        }
+    })
+}
+
+/// Rotate the log file: `log.log` becomes `log.log.1`, `log.log.1` becomes `log.log.2`
+/// and so on, and the files beyond `retention` are deleted
+fn rotate(path: &Path, retention: u32) -> io::Result<()> {
+    let rotated = |n: u32| {
+        let mut rotated = OsString::from(path);
+        rotated.push(format!(".{}", n));
+        PathBuf::from(rotated)
+    };
+
+    // Also deletes the files left over from a greater retention
+    for n in retention.max(1).. {
+        match fs::remove_file(rotated(n)) {
+            Ok(()) => (),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => break,
+            Err(err) => return Err(err),
+        }
+    }
+    if retention == 0 {
+        return fs::remove_file(path);
+    }
+    for n in (1..retention).rev() {
+        match fs::rename(rotated(n), rotated(n + 1)) {
+            Err(err) if err.kind() == io::ErrorKind::NotFound => (),
+            result => result?,
+        }
+    }
+    fs::rename(path, rotated(1))
+}
+
+/// Helper for [`log`] with debug level
+pub fn debug(msg: impl fmt::Display) {
+    log(Level::Debug, msg);
+}
+
+/// Helper for [`log`] with info level
+pub fn info(msg: impl fmt::Display) {
+    log(Level::Info, msg);
+}
+
+/// Helper for [`log`] with warn level
+pub fn warning(msg: impl fmt::Display) {
+    log(Level::Warn, msg);
+}
+
+/// Helper for [`log`] with error level. The app keeps running: see [`fatal`] to abort
+pub fn error(msg: impl fmt::Display) {
+    log(Level::Error, msg);
+}
+
+/// Helper for [`log`] with error level. This function panics!
+/// Call this when the situation is unrecoverable
+pub fn fatal(err: impl fmt::Display) -> ! {
+    log(Level::Error, &err);
+    panic!("{}", err);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An empty folder of its own for each test
+    fn temp_folder() -> PathBuf {
+        let folder = std::env::temp_dir().join(format!("food-fortress-log-{}", ulid::Ulid::new()));
+        fs::create_dir_all(&folder).unwrap();
+        folder
+    }
+
+    fn settings(rotation: LogRotation, max_size_kb: u64) -> Logging {
+        Logging {
+            rotation,
+            max_size_kb,
+            ..Logging::default()
+        }
+    }
+
+    fn day(day: u32) -> chrono::NaiveDate {
+        chrono::NaiveDate::from_ymd_opt(2023, 1, day).unwrap()
+    }
+
+    #[test]
+    fn parse_json() {
+        let entry = Entry::parse(
+            r#"{"timestamp":"2023-01-05T10:00:00+01:00","level":"warn","message":"Low disk"}"#,
+        );
+        assert_eq!(entry.timestamp, "2023-01-05T10:00:00+01:00");
+        assert_eq!(entry.level, Some(Level::Warn));
+        assert_eq!(entry.message, "Low disk");
+    }
+
+    #[test]
+    fn parse_text() {
+        let entry = Entry::parse("2023-01-05 10:00:00:000 Error: The file: is gone");
+        assert_eq!(entry.timestamp, "2023-01-05 10:00:00:000");
+        assert_eq!(entry.level, Some(Level::Error));
+        assert_eq!(entry.message, "The file: is gone");
+    }
+
+    #[test]
+    fn parse_legacy_warning() {
+        let entry = Entry::parse("2022-12-31 23:59:59:999 Warning: Old format");
+        assert_eq!(entry.level, Some(Level::Warn));
+        assert_eq!(entry.message, "Old format");
+    }
+
+    #[test]
+    fn parse_continuation() {
+        for line in [
+            "  at the second line",
+            "2023-01-05 10:00:00:000 Trace: unknown",
+            "",
+        ] {
+            let entry = Entry::parse(line);
+            assert!(entry.timestamp.is_empty());
+            assert_eq!(entry.level, None);
+            assert_eq!(entry.message, line);
+        }
+    }
+
+    #[test]
+    fn rotation_not_due_for_missing_or_empty_file() {
+        let folder = temp_folder();
+        let path = folder.join("log.log");
+        let settings = settings(LogRotation::Size, 1);
+        assert!(!is_rotation_due(&settings, &path, 4096, day(2), Some(day(1))).unwrap());
+
+        fs::write(&path, "").unwrap();
+        assert!(!is_rotation_due(&settings, &path, 4096, day(2), Some(day(1))).unwrap());
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn rotation_by_size() {
+        let folder = temp_folder();
+        let path = folder.join("log.log");
+        fs::write(&path, vec![b'a'; 1000]).unwrap();
+        let settings = settings(LogRotation::Size, 1);
+        assert!(!is_rotation_due(&settings, &path, 24, day(1), None).unwrap());
+        assert!(is_rotation_due(&settings, &path, 25, day(1), None).unwrap());
+
+        let never = Logging {
+            rotation: LogRotation::Never,
+            ..settings
+        };
+        assert!(!is_rotation_due(&never, &path, 4096, day(1), None).unwrap());
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn rotation_daily() {
+        let folder = temp_folder();
+        let path = folder.join("log.log");
+        fs::write(&path, "line\n").unwrap();
+        let settings = settings(LogRotation::Daily, 1);
+        assert!(!is_rotation_due(&settings, &path, 1, day(2), Some(day(2))).unwrap());
+        assert!(is_rotation_due(&settings, &path, 1, day(2), Some(day(1))).unwrap());
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn rotate_shifts_and_drops_old_files() {
+        let folder = temp_folder();
+        let path = folder.join("log.log");
+        let rotated = |n: u32| folder.join(format!("log.log.{}", n));
+        fs::write(&path, "current").unwrap();
+        fs::write(rotated(1), "first").unwrap();
+        fs::write(rotated(2), "second").unwrap();
+        fs::write(rotated(3), "left over").unwrap();
+
+        rotate(&path, 2).unwrap();
+        assert!(!path.exists());
+        assert_eq!(fs::read_to_string(rotated(1)).unwrap(), "current");
+        assert_eq!(fs::read_to_string(rotated(2)).unwrap(), "first");
+        assert!(!rotated(3).exists());
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn rotate_without_retention_deletes() {
+        let folder = temp_folder();
+        let path = folder.join("log.log");
+        fs::write(&path, "current").unwrap();
+        fs::write(folder.join("log.log.1"), "first").unwrap();
+
+        rotate(&path, 0).unwrap();
+        assert!(!path.exists());
+        assert!(!folder.join("log.log.1").exists());
+        fs::remove_dir_all(folder).unwrap();
+    }
+}
@@ -0,0 +1,258 @@
+//! The nutrition mod holds the nutrition facts of the foods and the intake log: every food
+//! eaten, from the table or over MQTT, day by day, with the nutrients it brought, so that
+//! the GUI can sum up what was eaten today and on the previous days. The ingredients of a
+//! leftover are not recorded, the leftover is once eaten.
+
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::ops;
+
+use super::backend::Food;
+use super::clock;
+use super::config;
+use super::log;
+
+/// Grams the nutrition facts per 100 g are given for
+const REFERENCE_GRAMS: f32 = 100.0;
+
+/// What the nutrition facts are given for
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Basis {
+    /// 100 g of the food, as printed on most labels
+    #[default]
+    Per100g,
+
+    /// One unit of the food, such as a yogurt or an egg
+    PerUnit,
+}
+
+/// Energy and macronutrients
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Default)]
+#[serde(default)]
+pub struct Nutrients {
+    #[serde(deserialize_with = "finite")]
+    pub kcal: f32,
+
+    /// Grams of protein
+    #[serde(deserialize_with = "finite")]
+    pub protein: f32,
+
+    /// Grams of carbohydrates
+    #[serde(deserialize_with = "finite")]
+    pub carbs: f32,
+
+    /// Grams of fat
+    #[serde(deserialize_with = "finite")]
+    pub fat: f32,
+}
+
+impl Nutrients {
+    /// The [`Nutrients`] multiplied by a factor
+    #[inline]
+    pub fn scaled(self, factor: f32) -> Self {
+        Self {
+            kcal: self.kcal * factor,
+            protein: self.protein * factor,
+            carbs: self.carbs * factor,
+            fat: self.fat * factor,
+        }
+    }
+
+    /// Returns whether every value is a finite, non-negative number
+    #[inline]
+    pub fn is_valid(&self) -> bool {
+        [self.kcal, self.protein, self.carbs, self.fat]
+            .iter()
+            .all(|value| value.is_finite() && *value >= 0.0)
+    }
+}
+
+impl ops::Add for Nutrients {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            kcal: self.kcal + other.kcal,
+            protein: self.protein + other.protein,
+            carbs: self.carbs + other.carbs,
+            fat: self.fat + other.fat,
+        }
+    }
+}
+
+impl std::iter::Sum for Nutrients {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), ops::Add::add)
+    }
+}
+
+/// The nutrition facts of a [`Food`] or of a [`Product`](super::products::Product)
+#[derive(Deserialize, Serialize, Clone, PartialEq, Default)]
+pub struct Nutrition {
+    #[serde(default)]
+    pub basis: Basis,
+
+    #[serde(flatten)]
+    pub nutrients: Nutrients,
+
+    /// Weight of one unit in grams, when the facts are per 100 g. Without it, eating one
+    /// unit counts as eating 100 g
+    #[serde(default, deserialize_with = "finite_option")]
+    pub unit_grams: Option<f32>,
+}
+
+// Equality is reflexive since the values are never NaN: deserializing rejects the
+// numbers that are not finite, the Open Food Facts import skips them, and the GUI only
+// stores the nutrition facts that are valid
+impl Eq for Nutrition {}
+
+impl Nutrition {
+    /// Returns whether every value is a finite, non-negative number, and the weight of a
+    /// unit a positive one
+    #[inline]
+    pub fn is_valid(&self) -> bool {
+        self.nutrients.is_valid()
+            && self
+                .unit_grams
+                .map_or(true, |grams| grams.is_finite() && grams > 0.0)
+    }
+
+    /// The [`Nutrients`] brought by eating one unit of the food
+    pub fn per_unit(&self) -> Nutrients {
+        match self.basis {
+            Basis::PerUnit => self.nutrients,
+            Basis::Per100g => self
+                .nutrients
+                .scaled(self.unit_grams.unwrap_or(REFERENCE_GRAMS) / REFERENCE_GRAMS),
+        }
+    }
+}
+
+/// Deserialize a number, rejecting the ones that are not finite, such as those too large
+/// for an [`f32`]
+fn finite<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<f32, D::Error> {
+    let value = <f32 as serde::Deserialize>::deserialize(deserializer)?;
+    if value.is_finite() {
+        Ok(value)
+    } else {
+        Err(serde::de::Error::custom(format!(
+            "{} is not a finite number",
+            value
+        )))
+    }
+}
+
+/// Like [`finite`], for an optional number
+fn finite_option<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<f32>, D::Error> {
+    #[derive(Deserialize)]
+    struct Finite(#[serde(deserialize_with = "finite")] f32);
+
+    let value = <Option<Finite> as serde::Deserialize>::deserialize(deserializer)?;
+    Ok(value.map(|Finite(value)| value))
+}
+
+/// A [`Food`] eaten, and what it brought
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Intake {
+    pub name: String,
+
+    /// When the [`Food`] was eaten, in milliseconds since the Unix epoch
+    pub timestamp: i64,
+
+    /// [`None`] when the [`Food`] had no nutrition facts
+    pub nutrients: Option<Nutrients>,
+}
+
+/// The [`IntakeLog`] maps each day to the [`Intake`]s of the [`Food`]s eaten that day
+#[derive(Deserialize, Serialize, Default)]
+pub struct IntakeLog {
+    days: BTreeMap<chrono::NaiveDate, Vec<Intake>>,
+}
+
+impl IntakeLog {
+    /// Open the [`IntakeLog`]. A missing file simply means nothing has been eaten yet,
+    /// while any other error is logged and the process aborts
+    pub fn open() -> Self {
+        let file = match fs::File::open(&config::current().paths.intake) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Self::default(),
+            Err(err) => log::fatal(err),
+        };
+        serde_json::from_reader(io::BufReader::new(file)).unwrap_or_else(|err| log::fatal(err))
+    }
+
+    /// Update the [`IntakeLog`], overwriting the contents of the json file
+    pub fn update(&self) {
+        let contents = serde_json::to_string_pretty(self).unwrap_or_else(|err| log::fatal(err));
+        fs::write(&config::current().paths.intake, contents).unwrap_or_else(|err| log::fatal(err));
+    }
+
+    /// Record a [`Food`] eaten right now
+    pub fn record(mut self, food: &Food) -> Self {
+        let now = clock::now();
+        self.days
+            .entry(now.naive_local().date())
+            .or_default()
+            .push(Intake {
+                name: food.name.clone(),
+                timestamp: now.timestamp_millis(),
+                nutrients: food.nutrition.as_ref().map(Nutrition::per_unit),
+            });
+        self
+    }
+
+    /// Forget an [`Intake`] recorded by mistake, by its position among the ones of its day
+    pub fn remove(mut self, day: chrono::NaiveDate, index: usize) -> Self {
+        if let Some(intakes) = self.days.get_mut(&day) {
+            if index < intakes.len() {
+                intakes.remove(index);
+            }
+            if intakes.is_empty() {
+                self.days.remove(&day);
+            }
+        }
+        self
+    }
+
+    /// The [`Intake`]s of a day, in the order the [`Food`]s were eaten
+    #[inline]
+    pub fn day(&self, day: chrono::NaiveDate) -> &[Intake] {
+        self.days.get(&day).map_or(&[], Vec::as_slice)
+    }
+
+    /// The [`Nutrients`] of a day, leaving out the [`Food`]s without nutrition facts
+    #[inline]
+    pub fn total(&self, day: chrono::NaiveDate) -> Nutrients {
+        self.day(day)
+            .iter()
+            .filter_map(|intake| intake.nutrients)
+            .sum()
+    }
+
+    /// The days something was eaten, the most recent first
+    #[inline]
+    pub fn days(&self) -> impl Iterator<Item = chrono::NaiveDate> + '_ {
+        self.days.keys().rev().copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_that_are_not_finite_are_rejected() {
+        let nutrition: Nutrition =
+            serde_json::from_str(r#"{"kcal": 52.0, "unit_grams": 180.0}"#).unwrap();
+        assert!(nutrition == nutrition.clone());
+        assert!(nutrition.is_valid());
+
+        assert!(serde_json::from_str::<Nutrition>(r#"{"kcal": 1e39}"#).is_err());
+        assert!(serde_json::from_str::<Nutrition>(r#"{"kcal": 1.0, "unit_grams": 1e39}"#).is_err());
+    }
+}
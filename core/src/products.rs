@@ -0,0 +1,281 @@
+//! The products mod is a small offline product database, used to look up EAN/UPC barcodes
+//! typed by a keyboard-wedge scanner. It can be filled by importing an Open Food Facts CSV
+//! dump, nutrition facts included, and it learns the products the user adds over time.
+
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, BufRead};
+
+use super::config;
+use super::log;
+use super::nutrition::{Basis, Nutrients, Nutrition};
+
+/// Valid barcode lengths: EAN-8, UPC-A, EAN-13 and GTIN-14
+const BARCODE_LENGTHS: [usize; 4] = [8, 12, 13, 14];
+
+/// A [`Product`] is what we know about a barcode
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Product {
+    pub name: String,
+    pub category: Option<String>,
+
+    /// Typical shelf life in days, if known
+    pub shelf_life: Option<u16>,
+
+    #[serde(default)]
+    pub nutrition: Option<Nutrition>,
+
+    /// true when the [`Product`] has been learned from the user rather than imported.
+    /// Learned products are never overwritten by an import
+    #[serde(default)]
+    pub learned: bool,
+}
+
+/// The [`ProductDatabase`] maps normalized barcodes to [`Product`]s
+#[derive(Deserialize, Serialize, Default)]
+pub struct ProductDatabase {
+    products: BTreeMap<String, Product>,
+}
+
+impl ProductDatabase {
+    /// Open the [`ProductDatabase`]. A missing file simply means an empty database, while
+    /// any other error is logged and the process aborts
+    pub fn open() -> Self {
+        let file = match fs::File::open(&config::current().paths.products) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Self::default(),
+            Err(err) => log::fatal(err),
+        };
+        serde_json::from_reader(io::BufReader::new(file)).unwrap_or_else(|err| log::fatal(err))
+    }
+
+    /// How many [`Product`]s there are
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.products.len()
+    }
+
+    /// Returns whether there are no [`Product`]s
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.products.is_empty()
+    }
+
+    /// Update the [`ProductDatabase`], overwriting the contents of the json file
+    pub fn update(&self) {
+        let contents = serde_json::to_string(self).unwrap_or_else(|err| log::fatal(err));
+        fs::write(&config::current().paths.products, contents)
+            .unwrap_or_else(|err| log::fatal(err));
+    }
+
+    /// Look up a barcode. The barcode is normalized first, so UPC-A and EAN-13 codes match
+    #[inline]
+    pub fn lookup(&self, barcode: &str) -> Option<&Product> {
+        self.products.get(&normalize(barcode))
+    }
+
+    /// Learn a [`Product`] the user has just added, replacing whatever we knew about it
+    #[inline]
+    pub fn learn(mut self, barcode: &str, mut product: Product) -> Self {
+        product.learned = true;
+        self.products.insert(normalize(barcode), product);
+        self
+    }
+
+    /// Import an Open Food Facts CSV dump. The official dump is tab separated, but comma
+    /// separated exports are accepted too. Rows without a valid barcode or a name are skipped.
+    /// Return the updated database and the number of imported products
+    pub fn import_open_food_facts(mut self, path: &str) -> io::Result<(Self, usize)> {
+        let mut lines = io::BufReader::new(fs::File::open(path)?).lines();
+        let header = match lines.next() {
+            Some(header) => header?,
+            None => return Ok((self, 0)),
+        };
+        let delimiter = if header.contains('\t') { '\t' } else { ',' };
+        let columns = split_record(&header, delimiter);
+        let column = |name: &str| columns.iter().position(|c| c == name);
+
+        let code_column = column("code").ok_or_else(|| missing_column("code"))?;
+        let name_column = column("product_name").ok_or_else(|| missing_column("product_name"))?;
+        let category_column = column("main_category_en").or_else(|| column("categories_en"));
+        let nutrient_columns = [
+            column("energy-kcal_100g"),
+            column("proteins_100g"),
+            column("carbohydrates_100g"),
+            column("fat_100g"),
+        ];
+        let quantity_column = column("product_quantity");
+
+        let mut imported = 0;
+        for line in lines {
+            let record = split_record(&line?, delimiter);
+            let (Some(code), Some(name)) = (record.get(code_column), record.get(name_column))
+            else {
+                continue;
+            };
+            let (code, name) = (normalize(code), name.trim());
+            if name.is_empty() || !is_valid_barcode(&code) {
+                continue;
+            }
+            if matches!(self.products.get(&code), Some(product) if product.learned) {
+                continue;
+            }
+
+            // Categories are comma separated, most generic first
+            let category = category_column
+                .and_then(|i| record.get(i))
+                .and_then(|c| c.split(',').next())
+                .map(|c| c.trim().to_string())
+                .filter(|c| !c.is_empty());
+            let number = |column: Option<usize>| {
+                column
+                    .and_then(|i| record.get(i))
+                    .and_then(|value| value.trim().parse::<f32>().ok())
+                    .filter(|value| value.is_finite() && *value >= 0.0)
+            };
+            let [kcal, protein, carbs, fat] = nutrient_columns.map(number);
+            // Products without energy have no usable nutrition facts
+            let nutrition = kcal.map(|kcal| Nutrition {
+                basis: Basis::Per100g,
+                nutrients: Nutrients {
+                    kcal,
+                    protein: protein.unwrap_or_default(),
+                    carbs: carbs.unwrap_or_default(),
+                    fat: fat.unwrap_or_default(),
+                },
+                // The quantity of the whole package, which is what a food of the table is
+                unit_grams: number(quantity_column).filter(|grams| *grams > 0.0),
+            });
+            self.products.insert(
+                code,
+                Product {
+                    name: name.to_string(),
+                    category,
+                    shelf_life: None,
+                    nutrition,
+                    learned: false,
+                },
+            );
+            imported += 1;
+        }
+        Ok((self, imported))
+    }
+}
+
+/// Normalize a barcode: trim it and turn a UPC-A code into the equivalent EAN-13 one
+#[inline]
+pub fn normalize(barcode: &str) -> String {
+    let barcode = barcode.trim();
+    if barcode.len() == 12 {
+        format!("0{}", barcode)
+    } else {
+        barcode.to_string()
+    }
+}
+
+/// Returns whether the barcode is a well formed EAN-8, UPC-A, EAN-13 or GTIN-14 code,
+/// check digit included
+pub fn is_valid_barcode(barcode: &str) -> bool {
+    if !BARCODE_LENGTHS.contains(&barcode.len()) || !barcode.bytes().all(|b| b.is_ascii_digit()) {
+        return false;
+    }
+
+    // From the right, excluding the check digit, weights alternate between 3 and 1
+    let digits = barcode
+        .bytes()
+        .map(|b| (b - b'0') as u32)
+        .collect::<Vec<_>>();
+    let (check, payload) = digits.split_last().unwrap(); // Guarded by length check
+    let sum = payload
+        .iter()
+        .rev()
+        .enumerate()
+        .fold(0, |sum, (i, d)| sum + if i % 2 == 0 { d * 3 } else { *d });
+    (10 - sum % 10) % 10 == *check
+}
+
+/// Split a CSV record, honouring double quotes
+fn split_record(line: &str, delimiter: char) -> Vec<String> {
+    // The tab separated dump is not quoted, and names may well contain quotes
+    if delimiter == '\t' {
+        return line.split('\t').map(String::from).collect();
+    }
+
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ch if ch == delimiter && !quoted => fields.push(std::mem::take(&mut field)),
+            ch => field.push(ch),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+/// Error returned when the CSV dump lacks a required column
+fn missing_column(name: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("The product dump has no '{}' column", name),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_barcodes() {
+        assert!(is_valid_barcode("96385074")); // EAN-8
+        assert!(is_valid_barcode("036000291452")); // UPC-A
+        assert!(is_valid_barcode("4006381333931")); // EAN-13
+        assert!(is_valid_barcode("00012345600012")); // GTIN-14
+                                                     // Leading zeros count as digits of the code
+        assert!(is_valid_barcode("0036000291452"));
+        assert!(is_valid_barcode("00000000"));
+    }
+
+    #[test]
+    fn invalid_barcodes() {
+        assert!(!is_valid_barcode("96385075"));
+        assert!(!is_valid_barcode("036000291453"));
+        assert!(!is_valid_barcode("4006381333932"));
+        assert!(!is_valid_barcode("400638133393a"));
+        assert!(!is_valid_barcode(" 4006381333931"));
+        assert!(!is_valid_barcode("40063813339310"));
+        assert!(!is_valid_barcode("1234567"));
+        assert!(!is_valid_barcode(""));
+    }
+
+    #[test]
+    fn normalized_barcodes() {
+        assert_eq!(normalize(" 036000291452\n"), "0036000291452");
+        assert_eq!(normalize("4006381333931"), "4006381333931");
+        assert_eq!(normalize("96385074"), "96385074");
+        assert!(is_valid_barcode(&normalize("036000291452")));
+    }
+
+    #[test]
+    fn split_records() {
+        assert_eq!(split_record("a,b,,c", ','), ["a", "b", "", "c"]);
+        assert_eq!(
+            split_record(r#"1,"Milk, whole","Say ""cheese""",x"#, ','),
+            ["1", "Milk, whole", r#"Say "cheese""#, "x"]
+        );
+        assert_eq!(split_record(r#""""#, ','), [""]);
+        // The tab separated dump is not quoted
+        assert_eq!(
+            split_record("1\t\"Milk\", whole\t", '\t'),
+            ["1", "\"Milk\", whole", ""]
+        );
+    }
+}
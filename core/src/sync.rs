@@ -0,0 +1,937 @@
+//! The sync mod lets several devices share an inventory through a synced folder, such as a
+//! Dropbox or Syncthing one. Instead of overwriting a shared json file, every device appends
+//! the changes it makes as [`Event`]s to a log file only it writes to. Opening the inventory
+//! replays the logs of all the devices in a deterministic order, so every device ends up
+//! with the same [`Food`]s. Old events are compacted into snapshots to keep the logs short.
+//!
+//! The synced folder holds one directory per inventory, containing:
+//! - `<device>.log.json`: the [`Event`]s of a device not compacted yet
+//! - `<device>.snapshot.json`: the [`Food`]s as a device last compacted them
+
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+
+use super::backend::{Food, FoodId, Foods};
+use super::clock;
+use super::config;
+use super::log;
+
+/// Extension of the log files
+const LOG_EXTENSION: &str = ".log.json";
+
+/// Extension of the snapshot files
+const SNAPSHOT_EXTENSION: &str = ".snapshot.json";
+
+/// Milliseconds in a day, used to compact events older than a number of days
+const DAY_MILLIS: i64 = 24 * 60 * 60 * 1000;
+
+/// The synced directories whose merge may be kept in memory, with the merge once made.
+/// See [`cache_merges`]
+static MERGES: Mutex<Vec<(PathBuf, Option<Merge>)>> = Mutex::new(Vec::new());
+
+/// The merged [`Food`]s of an inventory, and the conflicts found on the way
+type Merge = (Foods, Vec<Conflict>);
+
+/// A change made to an inventory by a device
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Event {
+    /// Device that made the change
+    pub device: String,
+
+    /// Position of the [`Event`] among the ones of its device, starting from 1
+    pub seq: u64,
+
+    /// When the change was made, in milliseconds since the Unix epoch
+    pub timestamp: i64,
+
+    pub op: Op,
+}
+
+/// What an [`Event`] did. Operations carry the whole [`Food`] as it was before the change,
+/// so that they still find it when its id has been reassigned on another device
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Op {
+    Add {
+        food: Food,
+    },
+    Open {
+        food: Food,
+    },
+
+    /// The [`Food`] has been eaten or thrown away
+    Remove {
+        food: Food,
+    },
+
+    /// Boxed to keep the [`Op`]s small, without changing the json
+    Edit {
+        before: Box<Food>,
+        after: Box<Food>,
+    },
+}
+
+/// The [`Food`]s of an inventory after compacting every [`Event`] up to `included`
+#[derive(Deserialize, Serialize, Default)]
+struct Snapshot {
+    /// Sequence number of the last [`Event`] compacted for each device
+    included: BTreeMap<String, u64>,
+    foods: Foods,
+}
+
+/// A conflict between the changes of two devices, found and resolved while merging
+#[derive(Clone, PartialEq, Eq)]
+pub enum Conflict {
+    /// The same [`Food`] was added on two devices, it is kept once
+    DuplicateAdd { name: String, device: String },
+
+    /// Two devices gave the same id to different [`Food`]s, the later one got a new id
+    IdClash {
+        name: String,
+        id: FoodId,
+        device: String,
+    },
+
+    /// A [`Food`] was eaten, opened or edited on a device after another device had
+    /// removed it. The [`Food`] stays removed
+    AlreadyRemoved { name: String, device: String },
+
+    /// A [`Food`] was edited on two devices, the latest edit wins
+    ConcurrentEdit { name: String, device: String },
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DuplicateAdd { name, device } => {
+                write!(f, "'{}' was added again by '{}', keeping it once", name, device)
+            }
+            Self::IdClash { name, id, device } => write!(
+                f,
+                "'{}' added by '{}' had the id {} of another food, giving it a new one",
+                name, device, id
+            ),
+            Self::AlreadyRemoved { name, device } => write!(
+                f,
+                "'{}' was changed by '{}' after being removed on another device, ignoring the change",
+                name, device
+            ),
+            Self::ConcurrentEdit { name, device } => write!(
+                f,
+                "'{}' was edited on several devices, keeping the edit of '{}'",
+                name, device
+            ),
+        }
+    }
+}
+
+/// A [`Replica`] is the view a device has of the synced directory of an inventory
+pub struct Replica {
+    dir: PathBuf,
+    device: String,
+    compact_after_days: u16,
+}
+
+impl Replica {
+    /// The [`Replica`] of the given inventory, or [`None`] when sync is disabled
+    pub fn of_inventory(name: &str) -> Option<Self> {
+        let config = config::current();
+        if !config.sync.enabled || config.sync.folder.trim().is_empty() {
+            return None;
+        }
+        Some(Self {
+            dir: Path::new(config.sync.folder.trim()).join(file_name(name)),
+            device: file_name(&config.sync.device),
+            compact_after_days: config.sync.compact_after_days,
+        })
+    }
+
+    /// The synced directory of the inventory
+    #[inline]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Start syncing an inventory on this device, unless it already has. The local
+    /// [`Food`]s missing from the synced folder are recorded as added by this device, so
+    /// that nothing is lost when joining a folder other devices already sync
+    pub fn join(&self, local: &[Food]) {
+        if self.has_joined() {
+            return;
+        }
+        let (synced, _) = self.merge();
+        let missing = local
+            .iter()
+            .filter(|food| !synced.iter().any(|f| f.id == food.id))
+            .cloned()
+            .collect::<Vec<_>>();
+        self.record(&[], &missing);
+
+        // Even without local foods, the log tells that this device has joined
+        if !self.path(LOG_EXTENSION).exists() {
+            fs::create_dir_all(&self.dir).unwrap_or_else(|err| log::fatal(err));
+            self.write(LOG_EXTENSION, &Vec::<Event>::new())
+                .unwrap_or_else(|err| log::fatal(err));
+        }
+        log::info(format!(
+            "Joined the synced folder '{}', adding {} local foods",
+            self.dir.display(),
+            missing.len()
+        ));
+    }
+
+    /// Returns whether this device syncs the inventory already, with a log or with
+    /// events compacted into a snapshot
+    fn has_joined(&self) -> bool {
+        self.path(LOG_EXTENSION).exists()
+            || self
+                .snapshots()
+                .values()
+                .any(|snapshot| snapshot.included.contains_key(&self.device))
+    }
+
+    /// Merge the snapshots and the logs of all the devices into the current [`Food`]s.
+    /// The merge only depends on the files, so every device gets the same result.
+    /// It is made again on every call, unless the directory is cached by [`cache_merges`]
+    pub fn merge(&self) -> (Foods, Vec<Conflict>) {
+        let mut merges = MERGES.lock().unwrap_or_else(PoisonError::into_inner);
+        match merges.iter_mut().find(|(dir, _)| *dir == self.dir) {
+            Some((_, Some(merge))) => merge.clone(),
+            Some((_, cached)) => cached.insert(self.merge_files()).clone(),
+            None => self.merge_files(),
+        }
+    }
+
+    /// Merge the snapshots and the logs as they are in the synced directory
+    fn merge_files(&self) -> (Foods, Vec<Conflict>) {
+        let snapshot = self
+            .snapshots()
+            .into_iter()
+            .max_by(|(a_device, a), (b_device, b)| {
+                let covered = |snapshot: &Snapshot| snapshot.included.values().sum::<u64>();
+                (covered(a), a_device).cmp(&(covered(b), b_device))
+            })
+            .map(|(_, snapshot)| snapshot)
+            .unwrap_or_default();
+
+        let events = self
+            .logs()
+            .into_values()
+            .flatten()
+            .filter(|event| event.seq > snapshot.included.get(&event.device).copied().unwrap_or(0))
+            .collect();
+        replay(snapshot.foods, events)
+    }
+
+    /// Record the changes between two versions of the [`Food`]s as [`Event`]s of this
+    /// device, log the conflicts they cause, then compact the log if it has grown old
+    pub fn record(&self, before: &[Food], after: &[Food]) {
+        let ops = diff(before, after);
+        if ops.is_empty() {
+            return;
+        }
+        let (_, known_conflicts) = self.merge();
+        fs::create_dir_all(&self.dir).unwrap_or_else(|err| log::fatal(err));
+
+        let mut events = self.own_log();
+        let first_seq = self.last_seq(&events) + 1;
+        // Devices order the events by their timestamps, so a pretended date must not leak
+        // into them
+        let timestamp = clock::system_millis();
+        for (seq, op) in (first_seq..).zip(ops) {
+            events.push(Event {
+                device: self.device.clone(),
+                seq,
+                timestamp,
+                op,
+            });
+        }
+        self.write(LOG_EXTENSION, &events)
+            .unwrap_or_else(|err| log::fatal(err));
+
+        let (_, conflicts) = self.merge();
+        for conflict in conflicts.iter().filter(|c| !known_conflicts.contains(c)) {
+            log::warning(format!(
+                "Sync conflict in '{}': {}",
+                self.dir.display(),
+                conflict
+            ));
+        }
+        self.compact(events);
+    }
+
+    /// Compact the log of this device when it has events older than `compact_after_days`.
+    /// Our events are only dropped once every snapshot includes them, so that any snapshot
+    /// can still be merged with the remaining events
+    fn compact(&self, events: Vec<Event>) {
+        let cutoff = clock::system_millis() - self.compact_after_days as i64 * DAY_MILLIS;
+        if events.iter().all(|event| event.timestamp >= cutoff) {
+            return;
+        }
+
+        let mut included = BTreeMap::new();
+        for event in self.logs().into_values().flatten() {
+            let seq = included.entry(event.device).or_insert(0);
+            *seq = event.seq.max(*seq);
+        }
+        for snapshot in self.snapshots().into_values() {
+            for (device, seq) in snapshot.included {
+                let included = included.entry(device).or_insert(0);
+                *included = seq.max(*included);
+            }
+        }
+        let (foods, _) = self.merge();
+        let snapshot = Snapshot { included, foods };
+        if let Err(err) = self.write(SNAPSHOT_EXTENSION, &snapshot) {
+            log::warning(format!("Sync snapshot could not be written: {}", err));
+            return;
+        }
+
+        let acknowledged = self
+            .snapshots()
+            .into_values()
+            .map(|snapshot| snapshot.included.get(&self.device).copied().unwrap_or(0))
+            .min()
+            .unwrap_or(0);
+        let kept = events
+            .into_iter()
+            .filter(|event| event.seq > acknowledged || event.timestamp >= cutoff)
+            .collect::<Vec<_>>();
+        self.write(LOG_EXTENSION, &kept)
+            .unwrap_or_else(|err| log::fatal(err));
+    }
+
+    /// Sequence number of the last [`Event`] of this device, even if already compacted
+    fn last_seq(&self, events: &[Event]) -> u64 {
+        let compacted = self
+            .snapshots()
+            .into_values()
+            .filter_map(|snapshot| snapshot.included.get(&self.device).copied())
+            .max()
+            .unwrap_or(0);
+        events.last().map_or(0, |event| event.seq).max(compacted)
+    }
+
+    /// The log of this device. Unlike the logs of the other devices, it must be readable,
+    /// otherwise we would overwrite it and lose our changes
+    fn own_log(&self) -> Vec<Event> {
+        match fs::read_to_string(self.path(LOG_EXTENSION)) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|err| log::fatal(err)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => log::fatal(err),
+        }
+    }
+
+    /// The logs of all the devices, by device
+    fn logs(&self) -> BTreeMap<String, Vec<Event>> {
+        self.read_all(LOG_EXTENSION)
+    }
+
+    /// The snapshots of all the devices, by device
+    fn snapshots(&self) -> BTreeMap<String, Snapshot> {
+        self.read_all(SNAPSHOT_EXTENSION)
+    }
+
+    /// Read every file with the given extension. A file may be half synced, so unreadable
+    /// files are logged and skipped: they will be read once the sync completes
+    fn read_all<T: serde::de::DeserializeOwned>(&self, extension: &str) -> BTreeMap<String, T> {
+        let mut values = BTreeMap::new();
+        for (device, path) in self.files(extension) {
+            let value = fs::read_to_string(&path)
+                .map_err(|err| err.to_string())
+                .and_then(|contents| {
+                    serde_json::from_str(&contents).map_err(|err| err.to_string())
+                });
+            match value {
+                Ok(value) => {
+                    values.insert(device, value);
+                }
+                Err(err) => log::warning(format!(
+                    "Sync file '{}' could not be read, skipping it: {}",
+                    path.display(),
+                    err
+                )),
+            }
+        }
+        values
+    }
+
+    /// The files with the given extension, by device
+    fn files(&self, extension: &str) -> Vec<(String, PathBuf)> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(_) => return Vec::new(),
+        };
+        entries
+            .filter_map(Result::ok)
+            .filter_map(|entry| {
+                let name = entry.file_name().into_string().ok()?;
+                let device = name.strip_suffix(extension)?.to_string();
+                Some((device, entry.path()))
+            })
+            .collect()
+    }
+
+    /// Path to the file of this device with the given extension
+    #[inline]
+    fn path(&self, extension: &str) -> PathBuf {
+        self.dir.join(format!("{}{}", self.device, extension))
+    }
+
+    /// Write a file of this device, forgetting the cached merge it changes
+    fn write(&self, extension: &str, value: &impl serde::Serialize) -> io::Result<()> {
+        let written = write_json(&self.path(extension), value);
+        if let Some((_, cached)) = MERGES
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter_mut()
+            .find(|(dir, _)| *dir == self.dir)
+        {
+            *cached = None;
+        }
+        written
+    }
+}
+
+/// Keep the merges of the given synced directories in memory, instead of reading and
+/// replaying all their files whenever an inventory is opened. Only for apps watching these
+/// directories, which must call [`forget_merges`] when something changes in them. The
+/// changes of this device forget the merges by themselves
+pub fn cache_merges(dirs: &[PathBuf]) {
+    *MERGES.lock().unwrap_or_else(PoisonError::into_inner) =
+        dirs.iter().map(|dir| (dir.clone(), None)).collect();
+}
+
+/// Forget the cached merges, so that the next ones read the synced directories again
+pub fn forget_merges() {
+    for (_, cached) in MERGES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .iter_mut()
+    {
+        *cached = None;
+    }
+}
+
+/// Apply the [`Event`]s to the [`Food`]s in a deterministic order, whatever the order
+/// they are given in. The events of a device are applied in the order it made them, even
+/// if its clock went back, and the devices are interleaved by timestamp
+fn replay(mut foods: Foods, events: Vec<Event>) -> (Foods, Vec<Conflict>) {
+    let mut devices = BTreeMap::<String, Vec<Event>>::new();
+    for event in events {
+        devices.entry(event.device.clone()).or_default().push(event);
+    }
+    let mut devices = devices
+        .into_values()
+        .map(|mut events| {
+            events.sort_by_key(|event| event.seq);
+            events.into_iter().peekable()
+        })
+        .collect::<Vec<_>>();
+
+    let mut conflicts = Vec::new();
+    // Ties go to the first device by name, since min_by_key keeps the first minimum
+    while let Some(next) = devices
+        .iter_mut()
+        .filter_map(|events| {
+            let timestamp = events.peek()?.timestamp;
+            Some((timestamp, events))
+        })
+        .min_by_key(|(timestamp, _)| *timestamp)
+        .and_then(|(_, events)| events.next())
+    {
+        apply(&mut foods, next, &mut conflicts);
+    }
+    foods.sort();
+    (foods, conflicts)
+}
+
+/// Apply an [`Event`] to the [`Food`]s, resolving conflicts deterministically
+fn apply(foods: &mut Foods, event: Event, conflicts: &mut Vec<Conflict>) {
+    let new_id = derived_id(&event);
+    let device = event.device;
+    match event.op {
+        Op::Add { mut food } => {
+            if foods.contains(&food) {
+                conflicts.push(Conflict::DuplicateAdd {
+                    name: food.name,
+                    device,
+                });
+                return;
+            }
+            if foods.iter().any(|f| f.id == food.id) {
+                let id = food.id;
+                food.id = new_id;
+                conflicts.push(Conflict::IdClash {
+                    name: food.name.clone(),
+                    id,
+                    device,
+                });
+            }
+            foods.push(food);
+        }
+        // Opening a food already opened on another device changes nothing
+        Op::Open { food } => match find(foods, &food) {
+            Some(i) => foods[i].open = true,
+            None => conflicts.push(Conflict::AlreadyRemoved {
+                name: food.name,
+                device,
+            }),
+        },
+        Op::Remove { food } => match find(foods, &food) {
+            Some(i) => {
+                foods.remove(i);
+            }
+            None => conflicts.push(Conflict::AlreadyRemoved {
+                name: food.name,
+                device,
+            }),
+        },
+        Op::Edit { before, mut after } => match find(foods, &before) {
+            Some(i) => {
+                if !same(&foods[i], &before) {
+                    conflicts.push(Conflict::ConcurrentEdit {
+                        name: after.name.clone(),
+                        device,
+                    });
+                }
+                after.id = foods[i].id;
+                foods[i] = *after;
+            }
+            None => conflicts.push(Conflict::AlreadyRemoved {
+                name: before.name,
+                device,
+            }),
+        },
+    }
+}
+
+/// A [`FoodId`] derived from an [`Event`], used when the id of an added [`Food`] is
+/// taken. It must not be random, so that every device picks the same one
+fn derived_id(event: &Event) -> FoodId {
+    // FNV-1a, which unlike the std hasher is guaranteed to never change
+    let hash = event
+        .device
+        .bytes()
+        .chain(event.seq.to_le_bytes())
+        .fold(0xcbf29ce484222325_u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        });
+    ulid::Ulid::from_parts(event.timestamp as u64, hash as u128).into()
+}
+
+/// Find a [`Food`] by id, even when it has been edited on another device in the meantime.
+/// Only when no [`Food`] has its id anymore, because the id has been migrated on another
+/// device, is it found by its fields
+fn find(foods: &[Food], food: &Food) -> Option<usize> {
+    foods
+        .iter()
+        .position(|f| f.id == food.id)
+        .or_else(|| foods.iter().position(|f| same(f, food)))
+}
+
+/// Whether the [`Food`]s are the same but for their id and whether they are open, since
+/// another device may have opened it in the meantime
+fn same(a: &Food, b: &Food) -> bool {
+    Food {
+        open: b.open,
+        id: b.id,
+        ..a.clone()
+    } == *b
+}
+
+/// The operations turning `before` into `after`. Unchanged [`Food`]s are paired first,
+/// then the remaining ones are matched by id
+fn diff(before: &[Food], after: &[Food]) -> Vec<Op> {
+    let mut added = after.iter().collect::<Vec<_>>();
+    let mut changed = Vec::new();
+    for old in before {
+        match added.iter().position(|new| *new == old) {
+            Some(i) => {
+                added.remove(i);
+            }
+            None => changed.push(old),
+        }
+    }
+
+    let mut ops = Vec::new();
+    for old in changed {
+        let Some(i) = added.iter().position(|new| new.id == old.id) else {
+            ops.push(Op::Remove { food: old.clone() });
+            continue;
+        };
+        let new = added.remove(i);
+        if !old.open
+            && *new
+                == (Food {
+                    open: true,
+                    ..old.clone()
+                })
+        {
+            ops.push(Op::Open { food: old.clone() });
+        } else {
+            ops.push(Op::Edit {
+                before: Box::new(old.clone()),
+                after: Box::new(new.clone()),
+            });
+        }
+    }
+    ops.extend(added.into_iter().map(|new| Op::Add { food: new.clone() }));
+    ops
+}
+
+/// Write a value as json through a temporary file, so that sync clients never pick up a
+/// half written file
+fn write_json(path: &Path, value: &impl serde::Serialize) -> io::Result<()> {
+    let contents = serde_json::to_string_pretty(value)?;
+    let temporary = path.with_extension("tmp");
+    fs::write(&temporary, contents)?;
+    fs::rename(temporary, path)
+}
+
+/// Turn a name into something usable as a file name
+fn file_name(name: &str) -> String {
+    name.trim()
+        .to_lowercase()
+        .chars()
+        .map(|ch| {
+            if ch.is_ascii_alphanumeric() || ch == '-' {
+                ch
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::BestBefore;
+    use crate::config::Logging;
+
+    fn food(name: &str, day: u32) -> Food {
+        let date = chrono::NaiveDate::from_ymd_opt(2023, 3, day).unwrap();
+        Food {
+            id: FoodId::new(),
+            ..Food::new(name.to_string(), BestBefore::from(date))
+        }
+    }
+
+    fn event(device: &str, seq: u64, timestamp: i64, op: Op) -> Event {
+        Event {
+            device: device.to_string(),
+            seq,
+            timestamp,
+            op,
+        }
+    }
+
+    fn sorted(mut foods: Foods) -> Foods {
+        foods.sort();
+        foods
+    }
+
+    /// A [`Replica`] of its own empty folder, logging there too rather than to the log
+    /// file of the app
+    fn replica(device: &str) -> Replica {
+        let dir = std::env::temp_dir().join(format!("food-fortress-sync-{}", ulid::Ulid::new()));
+        fs::create_dir_all(&dir).unwrap();
+        log::configure(Logging {
+            path: dir.join("log.log").display().to_string(),
+            ..Logging::default()
+        });
+        Replica {
+            dir,
+            device: device.to_string(),
+            compact_after_days: 30,
+        }
+    }
+
+    #[test]
+    fn diff_finds_every_change() {
+        let milk = food("Milk", 1);
+        let eggs = food("Eggs", 2);
+        let ham = food("Ham", 3);
+        let opened_milk = Food {
+            open: true,
+            ..milk.clone()
+        };
+        let renamed_eggs = Food {
+            name: "Free range eggs".to_string(),
+            ..eggs.clone()
+        };
+        let bread = food("Bread", 4);
+
+        let ops = diff(
+            &[milk.clone(), eggs.clone(), ham.clone()],
+            &[opened_milk, renamed_eggs.clone(), bread.clone()],
+        );
+        assert_eq!(ops.len(), 4);
+        assert!(matches!(&ops[0], Op::Open { food } if *food == milk));
+        assert!(matches!(&ops[1], Op::Edit { before, after }
+            if **before == eggs && **after == renamed_eggs));
+        assert!(matches!(&ops[2], Op::Remove { food } if *food == ham));
+        assert!(matches!(&ops[3], Op::Add { food } if *food == bread));
+        let unchanged = vec![bread];
+        assert!(diff(&unchanged, &unchanged).is_empty());
+    }
+
+    #[test]
+    fn applying_a_diff_gives_the_new_foods() {
+        let before = vec![food("Milk", 1), food("Eggs", 2), food("Ham", 3)];
+        let mut after = before.clone();
+        after[0].open = true;
+        after[1].best_before =
+            BestBefore::from(chrono::NaiveDate::from_ymd_opt(2023, 4, 1).unwrap());
+        after.remove(2);
+        after.push(food("Bread", 4));
+
+        let events = diff(&before, &after)
+            .into_iter()
+            .enumerate()
+            .map(|(i, op)| event("a", i as u64 + 1, 1000, op))
+            .collect();
+        let (foods, conflicts) = replay(before, events);
+        assert!(foods == sorted(after));
+        assert!(conflicts.is_empty());
+    }
+
+    #[test]
+    fn food_edited_on_a_device_and_eaten_on_another() {
+        let milk = food("Milk", 1);
+        let eggs = food("Eggs", 2);
+        let edited = Food {
+            name: "Oat milk".to_string(),
+            ..milk.clone()
+        };
+        let events = vec![
+            event(
+                "a",
+                1,
+                1000,
+                Op::Edit {
+                    before: Box::new(milk.clone()),
+                    after: Box::new(edited),
+                },
+            ),
+            event("b", 1, 2000, Op::Remove { food: milk.clone() }),
+        ];
+
+        let (foods, conflicts) = replay(vec![milk, eggs.clone()], events);
+        assert!(foods == vec![eggs]);
+        assert!(conflicts.is_empty());
+    }
+
+    #[test]
+    fn same_food_eaten_on_two_devices() {
+        let milk = food("Milk", 1);
+        let eggs = food("Eggs", 2);
+        let events = vec![
+            event("b", 1, 2000, Op::Remove { food: milk.clone() }),
+            event("a", 1, 1000, Op::Remove { food: milk.clone() }),
+        ];
+
+        let (foods, conflicts) = replay(vec![milk.clone(), eggs.clone()], events);
+        assert!(foods == vec![eggs]);
+        assert!(
+            conflicts
+                == vec![Conflict::AlreadyRemoved {
+                    name: milk.name,
+                    device: "b".to_string(),
+                }]
+        );
+    }
+
+    #[test]
+    fn events_of_a_device_keep_their_order_when_its_clock_goes_back() {
+        let milk = food("Milk", 1);
+        let eggs = food("Eggs", 2);
+        let events = vec![
+            event("a", 1, 2000, Op::Add { food: milk.clone() }),
+            event("a", 2, 1000, Op::Remove { food: milk }),
+            event("b", 1, 1500, Op::Add { food: eggs.clone() }),
+        ];
+
+        let (foods, conflicts) = replay(Vec::new(), events);
+        assert!(foods == vec![eggs]);
+        assert!(conflicts.is_empty());
+    }
+
+    #[test]
+    fn concurrent_edits_keep_the_latest() {
+        let milk = food("Milk", 1);
+        let renamed = Food {
+            name: "Whole milk".to_string(),
+            ..milk.clone()
+        };
+        let postponed = Food {
+            best_before: BestBefore::from(chrono::NaiveDate::from_ymd_opt(2023, 3, 9).unwrap()),
+            ..milk.clone()
+        };
+        let events = vec![
+            event(
+                "a",
+                1,
+                1000,
+                Op::Edit {
+                    before: Box::new(milk.clone()),
+                    after: Box::new(renamed),
+                },
+            ),
+            event(
+                "b",
+                1,
+                2000,
+                Op::Edit {
+                    before: Box::new(milk.clone()),
+                    after: Box::new(postponed.clone()),
+                },
+            ),
+        ];
+
+        let (foods, conflicts) = replay(vec![milk.clone()], events.clone());
+        assert!(foods == vec![postponed]);
+        assert!(
+            conflicts
+                == vec![Conflict::ConcurrentEdit {
+                    name: "Milk".to_string(),
+                    device: "b".to_string(),
+                }]
+        );
+
+        // Every device replays the events in the same order, whatever order it reads them
+        let (reversed, _) = replay(vec![milk], events.into_iter().rev().collect());
+        assert!(reversed == foods);
+    }
+
+    #[test]
+    fn duplicate_adds_and_id_clashes() {
+        let milk = food("Milk", 1);
+        let eggs = Food {
+            id: milk.id,
+            ..food("Eggs", 2)
+        };
+        let events = vec![
+            event("a", 1, 1000, Op::Add { food: milk.clone() }),
+            event("b", 1, 2000, Op::Add { food: milk.clone() }),
+            event("b", 2, 3000, Op::Add { food: eggs.clone() }),
+        ];
+
+        let (foods, conflicts) = replay(Foods::new(), events.clone());
+        assert_eq!(foods.len(), 2);
+        let new_eggs = foods.iter().find(|food| food.name == "Eggs").unwrap();
+        assert_ne!(new_eggs.id, milk.id);
+        assert_eq!(new_eggs.id, derived_id(&events[2]));
+        assert!(
+            conflicts
+                == vec![
+                    Conflict::DuplicateAdd {
+                        name: "Milk".to_string(),
+                        device: "b".to_string(),
+                    },
+                    Conflict::IdClash {
+                        name: "Eggs".to_string(),
+                        id: milk.id,
+                        device: "b".to_string(),
+                    },
+                ]
+        );
+    }
+
+    #[test]
+    fn compaction_keeps_the_merge() {
+        let replica = replica("a");
+        let old = clock::system_millis() - 60 * DAY_MILLIS;
+        let milk = food("Milk", 1);
+        let eggs = food("Eggs", 2);
+        let ham = food("Ham", 3);
+        let own = vec![
+            event("a", 1, old, Op::Add { food: milk.clone() }),
+            event("a", 2, old + 2, Op::Open { food: milk.clone() }),
+            event("a", 3, old + DAY_MILLIS * 59, Op::Add { food: ham }),
+        ];
+        let other = vec![
+            event("b", 1, old + 1, Op::Add { food: eggs.clone() }),
+            event("b", 2, old + 3, Op::Remove { food: eggs }),
+        ];
+        write_json(&replica.path(LOG_EXTENSION), &own).unwrap();
+        write_json(&replica.dir.join("b.log.json"), &other).unwrap();
+
+        let merged = replica.merge();
+        replica.compact(own);
+        let compacted = replica.merge();
+        assert!(compacted.0 == merged.0);
+        assert!(compacted.1.is_empty());
+        assert!(replica.path(SNAPSHOT_EXTENSION).exists());
+        // Only the recent event is left in the log
+        let kept = replica.own_log();
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].seq, 3);
+        assert_eq!(replica.last_seq(&kept), 3);
+        fs::remove_dir_all(replica.dir).unwrap();
+    }
+
+    #[test]
+    fn joining_keeps_the_local_foods() {
+        let replica = replica("a");
+        let milk = food("Milk", 1);
+        let bread = food("Bread", 2);
+        let other = vec![event("b", 1, 1000, Op::Add { food: milk.clone() })];
+        write_json(&replica.dir.join("b.log.json"), &other).unwrap();
+
+        replica.join(&[milk.clone(), bread.clone()]);
+        let (foods, conflicts) = replica.merge();
+        assert!(foods == sorted(vec![milk.clone(), bread]));
+        assert!(conflicts.is_empty());
+
+        // Once joined, the local json file is only a copy of the synced foods
+        replica.join(&[food("Ham", 3)]);
+        assert_eq!(replica.merge().0.len(), 2);
+        fs::remove_dir_all(replica.dir).unwrap();
+    }
+
+    #[test]
+    fn joining_without_local_foods() {
+        let replica = replica("a");
+        replica.join(&[]);
+        assert!(replica.path(LOG_EXTENSION).exists());
+        assert!(replica.has_joined());
+        fs::remove_dir_all(replica.dir).unwrap();
+    }
+
+    #[test]
+    fn cached_merges_are_kept_until_forgotten() {
+        let replica = replica("a");
+        let milk = vec![food("Milk", 1)];
+        let bread = vec![food("Bread", 2)];
+        let both = sorted(vec![milk[0].clone(), bread[0].clone()]);
+        cache_merges(std::slice::from_ref(&replica.dir));
+        replica.record(&[], &milk);
+        assert!(replica.merge().0 == milk);
+
+        // Changes of other devices wait for the watcher, ours are merged right away
+        let other = vec![event(
+            "b",
+            1,
+            1000,
+            Op::Add {
+                food: bread[0].clone(),
+            },
+        )];
+        write_json(&replica.dir.join("b.log.json"), &other).unwrap();
+        assert!(replica.merge().0 == milk);
+        forget_merges();
+        assert!(replica.merge().0 == both);
+        replica.record(&both, &bread);
+        assert!(replica.merge().0 == bread);
+
+        cache_merges(&[]);
+        fs::remove_dir_all(replica.dir).unwrap();
+    }
+}
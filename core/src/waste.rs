@@ -0,0 +1,163 @@
+//! The waste mod remembers the foods thrown away rather than eaten, along with what they
+//! cost, so that the GUI can report the money wasted each month and per category, and how
+//! much less was wasted than the month before.
+
+use chrono::Datelike;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+
+use super::backend::Food;
+use super::clock;
+use super::config;
+use super::log;
+
+/// Category of the [`Discarded`] foods without one, in the reports
+const UNCATEGORIZED: &str = "Uncategorized";
+
+/// A [`Food`] thrown away
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Discarded {
+    pub name: String,
+    pub category: Option<String>,
+    pub store: Option<String>,
+
+    /// What the [`Food`] cost, in cents, [`None`] when unknown
+    pub price: Option<u32>,
+
+    /// When the [`Food`] was thrown away
+    pub day: chrono::NaiveDate,
+}
+
+/// The [`WasteLog`] holds every [`Discarded`] food, oldest first
+#[derive(Deserialize, Serialize, Default)]
+pub struct WasteLog {
+    discarded: Vec<Discarded>,
+}
+
+/// What was thrown away in a month
+pub struct MonthReport {
+    /// First day of the month
+    pub month: chrono::NaiveDate,
+
+    pub foods: usize,
+
+    /// How many of the foods have no price, and are left out of the money wasted
+    pub unpriced: usize,
+
+    /// Money wasted, in cents
+    pub wasted: u64,
+
+    /// How many foods and how much money, from the category
+    pub by_category: BTreeMap<String, (usize, u64)>,
+}
+
+impl WasteLog {
+    /// Open the [`WasteLog`]. A missing file simply means nothing has been thrown away yet,
+    /// while any other error is logged and the process aborts
+    pub fn open() -> Self {
+        let file = match fs::File::open(&config::current().paths.waste) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Self::default(),
+            Err(err) => log::fatal(err),
+        };
+        serde_json::from_reader(io::BufReader::new(file)).unwrap_or_else(|err| log::fatal(err))
+    }
+
+    /// Update the [`WasteLog`], overwriting the contents of the json file
+    pub fn update(&self) {
+        let contents = serde_json::to_string_pretty(self).unwrap_or_else(|err| log::fatal(err));
+        fs::write(&config::current().paths.waste, contents).unwrap_or_else(|err| log::fatal(err));
+    }
+
+    /// Record a [`Food`] thrown away today
+    pub fn record(mut self, food: &Food) -> Self {
+        self.discarded.push(Discarded {
+            name: food.name.clone(),
+            category: food.category.clone(),
+            store: food.store.clone(),
+            price: food.price,
+            day: clock::today(),
+        });
+        self
+    }
+
+    /// The first day of every month something was thrown away, the most recent first
+    pub fn months(&self) -> Vec<chrono::NaiveDate> {
+        let mut months = self
+            .discarded
+            .iter()
+            .map(|discarded| first_of_month(discarded.day))
+            .collect::<Vec<_>>();
+        months.sort_unstable_by(|a, b| b.cmp(a));
+        months.dedup();
+        months
+    }
+
+    /// What was thrown away in the month of the given day
+    pub fn report(&self, day: chrono::NaiveDate) -> MonthReport {
+        let month = first_of_month(day);
+        let mut report = MonthReport {
+            month,
+            foods: 0,
+            unpriced: 0,
+            wasted: 0,
+            by_category: BTreeMap::new(),
+        };
+        for discarded in self
+            .discarded
+            .iter()
+            .filter(|discarded| first_of_month(discarded.day) == month)
+        {
+            let price = discarded.price.unwrap_or_default() as u64;
+            report.foods += 1;
+            report.unpriced += discarded.price.is_none() as usize;
+            report.wasted += price;
+            let category = discarded.category.as_deref().unwrap_or(UNCATEGORIZED);
+            let (foods, wasted) = report.by_category.entry(category.to_string()).or_default();
+            *foods += 1;
+            *wasted += price;
+        }
+        report
+    }
+
+    /// Money wasted from a day to another, both included, in cents
+    pub fn wasted(&self, from: chrono::NaiveDate, to: chrono::NaiveDate) -> u64 {
+        self.discarded
+            .iter()
+            .filter(|discarded| (from..=to).contains(&discarded.day))
+            .filter_map(|discarded| discarded.price)
+            .map(u64::from)
+            .sum()
+    }
+
+    /// Money saved so far this month, in cents: how much less was wasted than in the same
+    /// days of the previous month. Negative when more was wasted
+    pub fn saved_vs_last_month(&self, today: chrono::NaiveDate) -> i64 {
+        let this_month = first_of_month(today);
+        let last_month_end = this_month.pred_opt().unwrap_or(this_month);
+        let last_month = first_of_month(last_month_end);
+        let last_month_today = last_month.with_day(today.day()).unwrap_or(last_month_end);
+        self.wasted(last_month, last_month_today) as i64 - self.wasted(this_month, today) as i64
+    }
+}
+
+/// First day of the month of a day
+#[inline]
+pub fn first_of_month(day: chrono::NaiveDate) -> chrono::NaiveDate {
+    day.with_day(1).unwrap() // Every month has a first day
+}
+
+/// An amount of money in cents, with the currency of the settings, such as `12.50 €`
+pub fn format_money(cents: i64) -> String {
+    let sign = if cents < 0 { "-" } else { "" };
+    let cents = cents.unsigned_abs();
+    format!(
+        "{}{}.{:02} {}",
+        sign,
+        cents / 100,
+        cents % 100,
+        config::current().currency
+    )
+}
@@ -0,0 +1,466 @@
+//! The diagnostics mod gathers what helps finding out why something goes wrong: where the
+//! files are, whether sounds can be played, what the inventories hold, and the end of the
+//! log. The Diagnostics window shows it, and it can be exported as a zip bundle to attach
+//! to a bug report. The bundle leaves out the foods themselves, and the passwords and
+//! secrets of the settings.
+
+use eframe::egui;
+use serde_derive::Serialize;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use super::backend::{FoodState, Fridge};
+use super::clock;
+use super::config::{self, CONFIG};
+use super::frontend::ICON;
+use super::known_foods::KnownFoods;
+use super::log;
+use super::products::ProductDatabase;
+use super::sound;
+
+/// How many lines of the log go in the bundle
+const BUNDLE_LOG_LINES: usize = 2000;
+
+/// The cargo features this build has been made with
+const FEATURES: [(&str, bool); 4] = [
+    ("gui", cfg!(feature = "gui")),
+    ("audio", cfg!(feature = "audio")),
+    ("server", cfg!(feature = "server")),
+    ("cli", cfg!(feature = "cli")),
+];
+
+/// A file of the app, and where it actually is
+pub struct ResolvedPath {
+    /// What the file is for
+    pub name: String,
+
+    /// Absolute: relative paths are resolved from the working directory
+    pub path: PathBuf,
+
+    pub exists: bool,
+}
+
+/// What an inventory holds, without the names of its foods
+#[derive(Serialize)]
+pub struct InventoryStats {
+    /// `json file`, or `json file, synced` when sync is enabled
+    pub store: &'static str,
+
+    pub foods: usize,
+    pub expired: usize,
+    pub close_from_expiring: usize,
+    pub far_from_expiring: usize,
+    pub open: usize,
+}
+
+/// The [`Report`] is everything the diagnostics show, except for the log
+pub struct Report {
+    pub paths: Vec<ResolvedPath>,
+
+    /// Whether sounds can be played
+    pub audio: String,
+
+    /// From the name of each inventory
+    pub inventories: Vec<(String, InventoryStats)>,
+
+    pub products: usize,
+    pub known_foods: usize,
+}
+
+impl Report {
+    /// Gather the [`Report`] from the settings and the data files
+    pub fn gather() -> Self {
+        let config = config::current();
+        let mut paths = vec![
+            ("Settings".to_string(), CONFIG.to_string()),
+            ("Log".to_string(), log::path()),
+            ("Icon".to_string(), ICON.to_string()),
+        ];
+        paths.extend(
+            config
+                .inventories
+                .iter()
+                .map(|(name, path)| (format!("Inventory '{}'", name), path.clone())),
+        );
+        paths.extend([
+            ("Products".to_string(), config.paths.products.clone()),
+            ("Known foods".to_string(), config.paths.known_foods.clone()),
+            ("Alerts".to_string(), config.paths.alerts.clone()),
+            (
+                "Notifications".to_string(),
+                config.paths.notifications.clone(),
+            ),
+            ("Digest".to_string(), config.paths.digest.clone()),
+            ("Webhooks".to_string(), config.paths.webhooks.clone()),
+            ("Intake".to_string(), config.paths.intake.clone()),
+            ("Waste".to_string(), config.paths.waste.clone()),
+        ]);
+        let sound = &config.sound;
+        paths.extend(
+            [
+                ("Food added sound", &sound.added),
+                ("Food opened sound", &sound.opened),
+                ("Food eaten sound", &sound.eaten),
+                ("Food discarded sound", &sound.discarded),
+                ("Foods expiring sound", &sound.expiry_alert),
+            ]
+            .into_iter()
+            .filter(|(_, path)| !path.trim().is_empty())
+            .map(|(name, path)| (name.to_string(), path.trim().to_string())),
+        );
+        if config.sync.enabled {
+            paths.push(("Sync folder".to_string(), config.sync.folder.clone()));
+        }
+
+        let inventories = config
+            .inventories
+            .keys()
+            .filter_map(|name| {
+                Some((
+                    name.clone(),
+                    InventoryStats::of(&Fridge::open_inventory(name)?),
+                ))
+            })
+            .collect();
+
+        Self {
+            paths: paths
+                .into_iter()
+                .map(|(name, path)| ResolvedPath::new(name, &path))
+                .collect(),
+            audio: if sound.enabled {
+                sound::status()
+            } else {
+                format!("Muted. {}", sound::status())
+            },
+            inventories,
+            products: ProductDatabase::open().len(),
+            known_foods: KnownFoods::open().len(),
+        }
+    }
+
+    /// The [`Report`] as plain text, along with the version of the app and the system
+    pub fn text(&self) -> String {
+        let features = FEATURES
+            .iter()
+            .filter(|(_, enabled)| *enabled)
+            .map(|(feature, _)| *feature)
+            .collect::<Vec<_>>();
+        let mut text = format!(
+            "Food Fortress {} on {} {}\nFeatures: {}\nAudio: {}\nProducts: {}\nKnown foods: {}\n\nFiles:\n",
+            env!("CARGO_PKG_VERSION"),
+            std::env::consts::OS,
+            std::env::consts::ARCH,
+            features.join(", "),
+            self.audio,
+            self.products,
+            self.known_foods
+        );
+        for path in &self.paths {
+            text.push_str(&format!(
+                "  {}: {}{}\n",
+                path.name,
+                path.path.display(),
+                if path.exists { "" } else { " (missing)" }
+            ));
+        }
+        text
+    }
+}
+
+impl ResolvedPath {
+    fn new(name: String, path: &str) -> Self {
+        let path = std::env::current_dir()
+            .map(|dir| dir.join(path))
+            .unwrap_or_else(|_| PathBuf::from(path));
+        let path = path.canonicalize().unwrap_or(path);
+        Self {
+            name,
+            exists: path.exists(),
+            path,
+        }
+    }
+}
+
+impl InventoryStats {
+    fn of(fridge: &Fridge) -> Self {
+        Self {
+            store: if fridge.synced_dir().is_some() {
+                "json file, synced"
+            } else {
+                "json file"
+            },
+            foods: fridge.len(),
+            expired: fridge.in_state(FoodState::Expired).count(),
+            close_from_expiring: fridge.in_state(FoodState::CloseFromExpiring).count(),
+            far_from_expiring: fridge.in_state(FoodState::FarFromExpiring).count(),
+            open: fridge.iter().filter(|food| food.open).count(),
+        }
+    }
+}
+
+/// Write the diagnostics bundle to the working directory, and return its path. The zip
+/// holds the [`Report`], the redacted settings, the end of the log and the
+/// [`InventoryStats`], which only count the foods
+pub fn export_bundle() -> Result<PathBuf, String> {
+    let report = Report::gather();
+    let settings =
+        toml::to_string_pretty(&config::current().redacted()).map_err(|err| err.to_string())?;
+    let log = log::tail(BUNDLE_LOG_LINES)
+        .map_err(|err| format!("the log cannot be read: {}", err))?
+        .join("\n");
+    let inventories = report
+        .inventories
+        .iter()
+        .map(|(_, stats)| stats)
+        .collect::<Vec<_>>();
+    let inventories = serde_json::to_string_pretty(&inventories).map_err(|err| err.to_string())?;
+
+    let path = PathBuf::from(format!(
+        "food-fortress-diagnostics-{}.zip",
+        clock::now().format("%Y%m%d-%H%M%S")
+    ));
+    write_zip(
+        &path,
+        [
+            ("diagnostics.txt", report.text()),
+            ("config.toml", settings),
+            ("log.txt", log),
+            ("inventories.json", inventories),
+        ],
+    )
+    .map_err(|err| format!("'{}' cannot be written: {}", path.display(), err))?;
+    log::info(format!(
+        "Diagnostics bundle written to '{}'",
+        path.display()
+    ));
+    Ok(path.canonicalize().unwrap_or(path))
+}
+
+/// Write the files, from their name to their contents, to a new zip archive
+fn write_zip<'a>(
+    path: &Path,
+    files: impl IntoIterator<Item = (&'a str, String)>,
+) -> zip::result::ZipResult<()> {
+    let mut zip = zip::ZipWriter::new(fs::File::create(path)?);
+    let options =
+        zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    for (name, contents) in files {
+        zip.start_file(name, options)?;
+        zip.write_all(contents.as_bytes())?;
+    }
+    zip.finish()?;
+    Ok(())
+}
+
+/// The [`DiagnosticsWindow`] shows the diagnostics [`Report`] and the end of the log,
+/// filtered by level and searched, and exports the diagnostics bundle
+pub struct DiagnosticsWindow {
+    report: Report,
+
+    /// The end of the log, oldest first
+    log: Vec<log::Entry>,
+
+    /// Size of the log file when it was last read, to read it again only once it changed
+    log_len: Option<u64>,
+
+    /// Entries below this level are hidden
+    min_level: log::Level,
+
+    /// Only the entries containing this text are shown, ignoring case
+    search: String,
+
+    /// When the size of the log was last checked
+    polled: Instant,
+
+    /// Outcome of the last export of the bundle
+    export_status: Option<Result<String, String>>,
+}
+
+impl DiagnosticsWindow {
+    /// How often the size of the log is checked while the window is open. The report
+    /// reads every data file, so it is only gathered again with the Refresh button
+    const POLL: Duration = Duration::from_secs(2);
+
+    /// How many lines of the log are shown
+    const LOG_LINES: usize = 500;
+
+    pub fn new() -> Self {
+        Self {
+            report: Report::gather(),
+            log: Self::read_log(),
+            log_len: Self::log_len(),
+            min_level: log::Level::Info,
+            search: String::new(),
+            polled: Instant::now(),
+            export_status: None,
+        }
+    }
+
+    /// Size of the log file, [`None`] if it cannot be read
+    fn log_len() -> Option<u64> {
+        fs::metadata(log::path())
+            .ok()
+            .map(|metadata| metadata.len())
+    }
+
+    /// The end of the log, with the error on its own line if it cannot be read
+    fn read_log() -> Vec<log::Entry> {
+        match log::tail(Self::LOG_LINES) {
+            Ok(lines) => lines.iter().map(|line| log::Entry::parse(line)).collect(),
+            Err(err) => vec![log::Entry {
+                timestamp: String::new(),
+                level: Some(log::Level::Error),
+                message: format!("The log cannot be read: {}", err),
+            }],
+        }
+    }
+
+    /// Show the window. Return false once it is closed
+    pub fn show(&mut self, ctx: &egui::Context) -> bool {
+        if self.polled.elapsed() >= Self::POLL {
+            let log_len = Self::log_len();
+            if log_len != self.log_len {
+                self.log = Self::read_log();
+                self.log_len = log_len;
+            }
+            self.polled = Instant::now();
+        }
+        ctx.request_repaint_after(Self::POLL);
+
+        let mut open = !ctx.input().key_pressed(egui::Key::Escape);
+        egui::Window::new("Diagnostics")
+            .open(&mut open)
+            .collapsible(false)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                self.log_ui(ui);
+                self.report_ui(ui);
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui
+                        .button("Refresh")
+                        .on_hover_text("Read the files and the inventories again")
+                        .clicked()
+                    {
+                        self.report = Report::gather();
+                    }
+                    if ui
+                        .button("Export bundle")
+                        .on_hover_text(
+                            "Zip of the settings without their secrets, the end of the log and \
+                             how many foods each inventory holds",
+                        )
+                        .clicked()
+                    {
+                        self.export_status =
+                            Some(export_bundle().map(|path| path.display().to_string()));
+                    }
+                    match &self.export_status {
+                        Some(Ok(path)) => {
+                            ui.label(format!("Written to {}", path));
+                        }
+                        Some(Err(err)) => {
+                            ui.colored_label(ui.visuals().error_fg_color, err);
+                        }
+                        None => (),
+                    }
+                });
+            });
+        open
+    }
+
+    /// The end of the log, filtered by level and searched
+    fn log_ui(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Log");
+        ui.horizontal(|ui| {
+            for level in log::Level::ALL {
+                ui.radio_value(&mut self.min_level, level, level.to_string());
+            }
+            ui.add(
+                egui::TextEdit::singleline(&mut self.search)
+                    .hint_text("Search")
+                    .desired_width(150.0),
+            );
+        });
+
+        let search = self.search.trim().to_lowercase();
+        egui::ScrollArea::vertical()
+            .id_source("diagnostics log")
+            .max_height(250.0)
+            .stick_to_bottom(true)
+            .show(ui, |ui| {
+                let visuals = ui.visuals().clone();
+                for entry in &self.log {
+                    // The lines that are not a message follow the level of the filter
+                    if entry.level.map_or(false, |level| level < self.min_level)
+                        || !entry.message.to_lowercase().contains(&search)
+                    {
+                        continue;
+                    }
+                    let color = match entry.level {
+                        Some(log::Level::Error) => visuals.error_fg_color,
+                        Some(log::Level::Warn) => visuals.warn_fg_color,
+                        Some(log::Level::Debug) => visuals.weak_text_color(),
+                        _ => visuals.text_color(),
+                    };
+                    let text = match entry.level {
+                        Some(level) => format!("{} {}: {}", entry.timestamp, level, entry.message),
+                        None => entry.message.clone(),
+                    };
+                    ui.label(egui::RichText::new(text).monospace().color(color));
+                }
+            });
+    }
+
+    /// Where the files are, whether sounds can be played and what the inventories hold
+    fn report_ui(&self, ui: &mut egui::Ui) {
+        ui.heading("Files");
+        egui::Grid::new("diagnostics paths grid")
+            .num_columns(2)
+            .striped(true)
+            .show(ui, |ui| {
+                for path in &self.report.paths {
+                    ui.label(&path.name);
+                    let text = path.path.display().to_string();
+                    if path.exists {
+                        ui.label(text);
+                    } else {
+                        ui.colored_label(ui.visuals().warn_fg_color, format!("{} (missing)", text));
+                    }
+                    ui.end_row();
+                }
+            });
+
+        ui.heading("Audio");
+        ui.label(&self.report.audio);
+
+        ui.heading("Inventories");
+        egui::Grid::new("diagnostics inventories grid")
+            .num_columns(6)
+            .striped(true)
+            .show(ui, |ui| {
+                for header in ["Inventory", "Store", "Foods", "Expired", "Expiring", "Open"] {
+                    ui.label(egui::RichText::new(header).strong());
+                }
+                ui.end_row();
+
+                for (name, stats) in &self.report.inventories {
+                    ui.label(name);
+                    ui.label(stats.store);
+                    ui.label(stats.foods.to_string());
+                    ui.label(stats.expired.to_string());
+                    ui.label(stats.close_from_expiring.to_string());
+                    ui.label(stats.open.to_string());
+                    ui.end_row();
+                }
+            });
+        ui.label(format!(
+            "{} products, {} known foods",
+            self.report.products, self.report.known_foods
+        ));
+    }
+}
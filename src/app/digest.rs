@@ -0,0 +1,301 @@
+//! The digest mod emails a summary of the expired and expiring foods of every inventory to
+//! the household, daily or weekly, through the SMTP server of the settings. The daemon
+//! sends it when due, `food-fortress digest` sends it right away and
+//! `food-fortress digest --dry-run` prints it instead of sending it.
+//!
+//! Any SMTP server works, including a local sink such as MailHog or
+//! `python -m aiosmtpd -n`, with `security = "none"`.
+
+use chrono::{Datelike, TimeZone};
+use lettre::message::{Mailbox, MultiPart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+use serde_derive::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+
+use super::alerts::AlertLevel;
+use super::backend::{self, today, Food};
+use super::config::{self, DigestFrequency, SmtpSecurity};
+use super::log;
+
+/// Overrides the SMTP password of the settings, so that it needn't be saved in clear
+const PASSWORD_VAR: &str = "FOOD_FORTRESS_SMTP_PASSWORD";
+
+/// The [`Digest`] lists the expired and expiring [`Food`]s of every inventory, along with
+/// the inventory they are in
+pub struct Digest {
+    expired: Vec<(String, Food)>,
+    expiring: Vec<(String, Food)>,
+}
+
+impl Digest {
+    /// Gather the [`Food`]s to report from every inventory
+    pub fn of_all_inventories() -> Self {
+        let mut digest = Self {
+            expired: Vec::new(),
+            expiring: Vec::new(),
+        };
+        for (inventory, food) in backend::all_foods() {
+            match AlertLevel::of(&food) {
+                Some(AlertLevel::Expired) => digest.expired.push((inventory, food)),
+                Some(AlertLevel::Expiring) => digest.expiring.push((inventory, food)),
+                None => (),
+            }
+        }
+        digest
+    }
+
+    /// Returns whether there is nothing to report
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.expired.is_empty() && self.expiring.is_empty()
+    }
+
+    /// Subject of the email, with how many foods are expired and expiring
+    pub fn subject(&self) -> String {
+        format!(
+            "Food Fortress: {} expired, {} expiring",
+            self.expired.len(),
+            self.expiring.len()
+        )
+    }
+
+    /// Plain text body of the email
+    pub fn text(&self) -> String {
+        let config = config::current();
+        let mut text = format!(
+            "Food Fortress digest of {}\n",
+            today().format(&config.date_format)
+        );
+        if self.is_empty() {
+            text.push_str("\nNothing is expiring, well done!\n");
+        }
+        for (title, foods) in self.sections() {
+            text.push_str(&format!("\n{} ({})\n", title, foods.len()));
+            for (inventory, food) in foods {
+                text.push_str(&format!(
+                    "  - {}\n",
+                    line(inventory, food, &config.date_format)
+                ));
+            }
+        }
+        text
+    }
+
+    /// HTML body of the email, coloured like the table of the app
+    pub fn html(&self) -> String {
+        let config = config::current();
+        let mut html = format!(
+            "<html><body style=\"font-family: sans-serif\">\n<h2>Food Fortress digest of {}</h2>\n",
+            escape(&today().format(&config.date_format).to_string())
+        );
+        if self.is_empty() {
+            html.push_str("<p>Nothing is expiring, well done!</p>\n");
+        }
+        for ((title, foods), [r, g, b]) in self.sections().into_iter().zip([
+            config.appearance.expired_color,
+            config.appearance.close_from_expiring_color,
+        ]) {
+            html.push_str(&format!(
+                "<h3 style=\"color: rgb({}, {}, {})\">{} ({})</h3>\n<ul>\n",
+                r,
+                g,
+                b,
+                title,
+                foods.len()
+            ));
+            for (inventory, food) in foods {
+                html.push_str(&format!(
+                    "<li>{}</li>\n",
+                    escape(&line(inventory, food, &config.date_format))
+                ));
+            }
+            html.push_str("</ul>\n");
+        }
+        html.push_str("</body></html>\n");
+        html
+    }
+
+    /// The non-empty sections of the digest, expired foods first
+    fn sections(&self) -> Vec<(&str, &[(String, Food)])> {
+        [
+            ("Expired", self.expired.as_slice()),
+            ("Expiring soon", self.expiring.as_slice()),
+        ]
+        .into_iter()
+        .filter(|(_, foods)| !foods.is_empty())
+        .collect()
+    }
+
+    /// The email to the recipients of the settings, with both a plain text and an HTML
+    /// body so that any mail client can show it
+    pub fn message(&self) -> Result<Message, String> {
+        let config = config::current();
+        let settings = &config.digest;
+        if settings.recipients.is_empty() {
+            return Err("there are no recipients in the settings".to_string());
+        }
+
+        let mut builder = Message::builder()
+            .from(mailbox(&settings.from)?)
+            .subject(self.subject());
+        for recipient in &settings.recipients {
+            builder = builder.to(mailbox(recipient)?);
+        }
+        builder
+            .multipart(MultiPart::alternative_plain_html(self.text(), self.html()))
+            .map_err(|err| err.to_string())
+    }
+}
+
+/// The [`Sent`] remembers when the last digest was sent
+#[derive(Deserialize, Serialize, Default)]
+struct Sent {
+    /// In milliseconds since the Unix epoch
+    last_sent: Option<i64>,
+}
+
+impl Sent {
+    /// Open the [`Sent`]. A missing file simply means no digest has been sent yet, while
+    /// any other error is logged and the process aborts
+    fn open() -> Self {
+        let file = match fs::File::open(&config::current().paths.digest) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Self::default(),
+            Err(err) => log::fatal(err),
+        };
+        serde_json::from_reader(file).unwrap_or_else(|err| log::fatal(err))
+    }
+
+    /// Update the [`Sent`], overwriting the contents of the json file
+    fn update(&self) {
+        let contents = serde_json::to_string_pretty(self).unwrap_or_else(|err| log::fatal(err));
+        fs::write(&config::current().paths.digest, contents).unwrap_or_else(|err| log::fatal(err));
+    }
+}
+
+/// Send the digest if it is enabled and has not been sent since it was last scheduled.
+/// An empty digest is not sent, but still counts as sent. Errors are logged, and the
+/// digest is tried again on the next call
+pub fn send_if_due() {
+    let config = config::current();
+    if !config.digest.enabled {
+        return;
+    }
+    let scheduled = last_scheduled(&config.digest, today());
+    if Sent::open()
+        .last_sent
+        .map_or(false, |sent| sent >= scheduled)
+    {
+        return;
+    }
+
+    let digest = Digest::of_all_inventories();
+    if !digest.is_empty() {
+        if let Err(err) = send(&digest) {
+            log::error(format!("The email digest could not be sent: {}", err));
+            return;
+        }
+        log::info(format!(
+            "The email digest has been sent: {}",
+            digest.subject()
+        ));
+    }
+    Sent {
+        last_sent: Some(today().timestamp_millis()),
+    }
+    .update();
+}
+
+/// Send the digest right away, whatever the schedule
+pub fn send_now() -> Result<(), String> {
+    send(&Digest::of_all_inventories())?;
+    Sent {
+        last_sent: Some(today().timestamp_millis()),
+    }
+    .update();
+    Ok(())
+}
+
+/// The digest as it would be sent, headers included
+pub fn dry_run() -> Result<String, String> {
+    let message = Digest::of_all_inventories().message()?;
+    Ok(String::from_utf8_lossy(&message.formatted()).into_owned())
+}
+
+/// Send the digest through the SMTP server of the settings
+fn send(digest: &Digest) -> Result<(), String> {
+    let message = digest.message()?;
+    let config = config::current();
+    let smtp = &config.digest.smtp;
+    if smtp.host.trim().is_empty() {
+        return Err("there is no SMTP server in the settings".to_string());
+    }
+
+    let host = smtp.host.trim();
+    let builder = match smtp.security {
+        SmtpSecurity::None => Ok(SmtpTransport::builder_dangerous(host)),
+        SmtpSecurity::StartTls => SmtpTransport::starttls_relay(host),
+        SmtpSecurity::Tls => SmtpTransport::relay(host),
+    };
+    let mut builder = builder.map_err(|err| err.to_string())?.port(smtp.port);
+    let password = std::env::var(PASSWORD_VAR).unwrap_or_else(|_| smtp.password.clone());
+    if !smtp.username.is_empty() {
+        builder = builder.credentials(Credentials::new(smtp.username.clone(), password));
+    }
+
+    builder
+        .build()
+        .send(&message)
+        .map(|_| ())
+        .map_err(|err| err.to_string())
+}
+
+/// When the digest was last scheduled before `now`, in milliseconds since the Unix epoch
+fn last_scheduled(settings: &config::Digest, now: chrono::DateTime<chrono::Local>) -> i64 {
+    let time = chrono::NaiveTime::from_hms_opt(settings.hour.min(23), 0, 0).unwrap_or_default();
+    let today = now.naive_local().date();
+    // A weekly digest was scheduled within the last 7 days, a daily one within the last 2
+    (0..8)
+        .filter_map(|days_ago| today.checked_sub_signed(chrono::Duration::days(days_ago)))
+        .filter(|date| match settings.frequency {
+            DigestFrequency::Daily => true,
+            DigestFrequency::Weekly => date.weekday() == settings.weekday,
+        })
+        .filter_map(|date| {
+            chrono::Local
+                .from_local_datetime(&date.and_time(time))
+                .earliest()
+        })
+        .find(|scheduled| *scheduled <= now)
+        .map_or(0, |scheduled| scheduled.timestamp_millis())
+}
+
+/// A mailbox of the settings, such as `fridge@example.com` or
+/// `Food Fortress <fridge@example.com>`
+fn mailbox(address: &str) -> Result<Mailbox, String> {
+    address
+        .trim()
+        .parse()
+        .map_err(|err| format!("invalid email address '{}': {}", address, err))
+}
+
+/// A [`Food`] of the digest, on one line
+fn line(inventory: &str, food: &Food, date_format: &str) -> String {
+    format!(
+        "{} ({}), best before {}{}",
+        food.name,
+        inventory,
+        food.best_before.format(date_format),
+        if food.open { ", open" } else { "" }
+    )
+}
+
+/// Escape the characters with a meaning in HTML
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
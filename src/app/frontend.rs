@@ -1,10 +1,41 @@
-//! The frontend module is responsible for display the GUI and handling events, 
+//! The frontend module is responsible for display the GUI and handling events,
 //! with the support of the backend.
 
+mod alerts;
+mod intake;
+mod settings;
+mod waste;
+
 use chrono::Datelike;
 use eframe::egui;
+use std::cmp;
+use std::collections::BTreeSet;
+use std::mem;
+use std::time::{Duration, Instant};
+
+use super::backend::{
+    self, BestBefore, Food, FoodId, FoodPatch, FoodState, Fridge, FridgeError, Ingredient, Leftover,
+};
+use super::clock;
+use super::config::{self, Config};
+use super::diagnostics::DiagnosticsWindow;
+use super::known_foods::KnownFoods;
+use super::log;
+use super::notifications;
+use super::nutrition::{Basis, Nutrition};
+use super::products::{self, Product, ProductDatabase};
+use super::sound::{self, SoundEvent};
+use super::sync;
+use super::watcher::FileWatcher;
+#[cfg(feature = "server")]
+use super::webhooks;
+use alerts::AlertsWindow;
+use intake::IntakeWindow;
+use settings::SettingsWindow;
+use waste::WasteWindow;
 
-use super::backend::{play_eating_sound, today, BestBefore, Food, FoodState, Fridge};
+/// Path to the icon of the window
+pub const ICON: &str = "images/refrigerator.png";
 
 /// Return an [`egui::Label`] and [`egui::widgets::DragValue`]
 macro_rules! new_label_and_drag_value {
@@ -27,24 +58,430 @@ macro_rules! new_label_and_drag_value {
 pub struct App {
     add_food_menu: AddFoodMenu,
     table: Table,
+
+    /// true when the keyboard shortcuts help is shown
+    show_shortcuts: bool,
+
+    /// The [`SettingsWindow`], if open
+    settings: Option<SettingsWindow>,
+
+    /// The [`DiagnosticsWindow`], if open
+    diagnostics: Option<DiagnosticsWindow>,
+
+    /// The [`IntakeWindow`], if open
+    intake: Option<IntakeWindow>,
+
+    /// The [`WasteWindow`], if open
+    waste: Option<WasteWindow>,
+
+    /// true when the foods of all the inventories are shown
+    show_all_inventories: bool,
+
+    /// true when the long stored foods are shown
+    show_ageing: bool,
+
+    /// Window size last applied from the [`Config`]
+    window_size: egui::Vec2,
+
+    /// Notices when the inventory is changed outside of the app
+    watcher: Option<FileWatcher>,
+
+    /// The [`AlertsWindow`], if shown
+    alerts: Option<AlertsWindow>,
+
+    /// When to check again for foods to alert about, [`None`] at startup
+    next_alert_check: Option<Instant>,
+
+    /// The date of the last frame, to notice when it rolls over at midnight
+    today: Option<chrono::NaiveDate>,
 }
 
 impl eframe::App for App {
     /// Main update
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        self.watch_files(ctx);
+        self.check_date(ctx);
+        self.check_alerts(ctx);
+        self.handle_shortcuts(ctx);
         egui::CentralPanel::default().show(ctx, |ui| {
-            self.add_food_menu.ui(ui);
+            ui.horizontal(|ui| {
+                self.add_food_menu.ui(ui);
+                self.today_and_settings_ui(ui);
+            });
             self.add_separator(ui);
             self.table.ui(ui);
         });
+        self.table.edit_food_window(ctx);
+        self.shortcuts_window(ctx);
+        self.settings_window(ctx);
+        self.diagnostics_window(ctx);
+        self.intake_window(ctx);
+        self.waste_window(ctx);
+        self.all_inventories_window(ctx);
+        self.ageing_window(ctx);
+        self.alerts_window(ctx);
+        self.apply_window_size(frame);
     }
 }
 
 impl App {
+    /// Keyboard shortcuts and what they do, shown in the help window
+    const SHORTCUTS: [(&'static str, &'static str); 16] = [
+        ("N", "Add a new food"),
+        ("/", "Search foods"),
+        ("Up / Down", "Select a food"),
+        ("Enter", "Open or eat the selected food"),
+        ("E", "Edit the selected food"),
+        ("Del", "Discard the selected food, after confirming"),
+        ("S", "Open the settings"),
+        ("I", "Show what was eaten"),
+        ("W", "Show the money wasted"),
+        ("A", "Show the long stored foods"),
+        ("D", "Show the diagnostics"),
+        ("M", "Mute or unmute the sounds"),
+        ("Tab", "Move to the next field"),
+        ("Enter (in a field)", "Add or save the food"),
+        ("Esc", "Leave a field or close a window"),
+        ("? / F1", "Show or hide this help"),
+    ];
+
     pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
         // Add the custom fonts
         setup_custom_fonts(&cc.egui_ctx);
-        Default::default()
+        let config = config::current();
+        Self {
+            window_size: egui::vec2(config.window.width, config.window.height),
+            ..Default::default()
+        }
+    }
+
+    /// Display the today date, the inventory switcher and the settings button, aligned
+    /// to the right
+    fn today_and_settings_ui(&mut self, ui: &mut egui::Ui) {
+        let config = config::current();
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
+            ui.add_space(20.0);
+            if ui.button("Settings").clicked() {
+                self.open_settings();
+            }
+            if ui
+                .button("Diagnostics")
+                .on_hover_text("Log, files and audio, to find out what went wrong")
+                .clicked()
+            {
+                self.toggle_diagnostics();
+            }
+            if ui
+                .button("Intake")
+                .on_hover_text("What was eaten each day, and its nutrients")
+                .clicked()
+            {
+                self.toggle_intake();
+            }
+            if ui
+                .button("Waste")
+                .on_hover_text("Money wasted on the foods thrown away")
+                .clicked()
+            {
+                self.toggle_waste();
+            }
+            if ui
+                .button("Ageing")
+                .on_hover_text("Foods stored for long, far from their best before")
+                .clicked()
+            {
+                self.show_ageing = !self.show_ageing;
+            }
+            if ui
+                .button("All")
+                .on_hover_text("Foods of all the inventories")
+                .clicked()
+            {
+                self.show_all_inventories = !self.show_all_inventories;
+            }
+            egui::ComboBox::from_id_source("inventory switcher")
+                .selected_text(config.inventory.as_str())
+                .show_ui(ui, |ui| {
+                    for name in config.inventories.keys() {
+                        if ui
+                            .selectable_label(*name == config.inventory, name.as_str())
+                            .clicked()
+                        {
+                            self.switch_inventory(name);
+                        }
+                    }
+                });
+            ui.add_space(10.0);
+            ui.add(egui::Label::new(
+                egui::RichText::new(BestBefore::today().format(&config.date_format))
+                    .strong()
+                    .heading()
+                    .font(egui::FontId::new(
+                        config.appearance.header_font_size - 2.0,
+                        egui::FontFamily::Proportional,
+                    )),
+            ));
+        });
+    }
+
+    /// Watch the files of the inventory in use and the logs the windows show, and reload
+    /// what the UI keeps in memory when they change. The table itself is read from the
+    /// files on every repaint, so it only needs the repaint requested by the
+    /// [`FileWatcher`]. The merge of a watched synced folder is kept until the folder changes
+    fn watch_files(&mut self, ctx: &egui::Context) {
+        let dirs = FileWatcher::dirs_to_watch();
+        if self.watcher.as_ref().map(FileWatcher::dirs) != Some(dirs.as_slice()) {
+            let watcher = FileWatcher::new(dirs, ctx);
+            sync::cache_merges(watcher.watched());
+            self.watcher = Some(watcher);
+        }
+        if self
+            .watcher
+            .as_ref()
+            .map_or(false, FileWatcher::take_changed)
+        {
+            sync::forget_merges();
+            self.table.reload();
+            if let Some(window) = &mut self.intake {
+                window.reload();
+            }
+            if let Some(window) = &mut self.waste {
+                window.reload();
+            }
+            if let Some(window) = &mut self.alerts {
+                window.reload();
+            }
+        }
+    }
+
+    /// Refresh what depends on the date when it rolls over while the app is open, waking the
+    /// UI up at midnight for it. The table computes the states on every repaint, so only
+    /// the default best before and the alerts need refreshing
+    fn check_date(&mut self, ctx: &egui::Context) {
+        let now = clock::now().naive_local();
+        let today = now.date();
+        if let Some(yesterday) = self.today.filter(|day| *day != today) {
+            self.add_food_menu.roll_over(yesterday);
+            if let Some(window) = &mut self.intake {
+                window.roll_over(yesterday);
+            }
+            if let Some(window) = &mut self.alerts {
+                window.reload();
+            }
+            self.next_alert_check = None;
+        }
+        self.today = Some(today);
+
+        let until_midnight = (today + chrono::Duration::days(1))
+            .and_hms_opt(0, 0, 0)
+            .and_then(|midnight| (midnight - now).to_std().ok())
+            .unwrap_or_default();
+        ctx.request_repaint_after(until_midnight + Duration::from_secs(1));
+    }
+
+    /// Show the expiry alerts at startup, then every `interval_minutes`, unless there is
+    /// nothing new to alert about or the alerts are snoozed. Desktop notifications and the
+    /// expiry webhooks are sent at the same time, for when the window is minimised
+    fn check_alerts(&mut self, ctx: &egui::Context) {
+        let now = Instant::now();
+        if self.next_alert_check.map_or(false, |next| now < next) {
+            return;
+        }
+        let config = config::current();
+        let interval = Duration::from_secs(config.alerts.interval_minutes.max(1) as u64 * 60);
+        self.next_alert_check = Some(now + interval);
+        ctx.request_repaint_after(interval);
+        notifications::notify_crossings();
+        #[cfg(feature = "server")]
+        webhooks::check_transitions();
+
+        if !config.alerts.enabled || self.alerts.is_some() {
+            return;
+        }
+        let window = AlertsWindow::new();
+        if window.is_due() {
+            self.alerts = Some(window);
+            sound::play(SoundEvent::ExpiryAlert);
+        }
+    }
+
+    /// Show the [`AlertsWindow`], if open
+    fn alerts_window(&mut self, ctx: &egui::Context) {
+        if let Some(window) = &mut self.alerts {
+            if !window.show(ctx) {
+                self.alerts = None;
+            }
+        }
+    }
+
+    /// Use another inventory, remembering it for the next time the app starts
+    fn switch_inventory(&mut self, name: &str) {
+        let mut config = Config::clone(&config::current());
+        if config.inventory == name {
+            return;
+        }
+        config.inventory = name.to_string();
+        config::save(config);
+        self.table.reset_selection();
+    }
+
+    /// Show the foods of all the inventories, the ones expiring first on top
+    fn all_inventories_window(&mut self, ctx: &egui::Context) {
+        let config = config::current();
+        egui::Window::new("All inventories")
+            .open(&mut self.show_all_inventories)
+            .collapsible(false)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                egui::Grid::new("all inventories grid")
+                    .num_columns(3)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.label(egui::RichText::new("Food").strong());
+                        ui.label(egui::RichText::new("Inventory").strong());
+                        ui.label(egui::RichText::new("Best before").strong());
+                        ui.end_row();
+
+                        for (inventory, food) in backend::all_foods() {
+                            ui.label(food.name);
+                            ui.label(inventory);
+                            ui.label(
+                                egui::RichText::new(food.best_before.format(&config.date_format))
+                                    .color(state_color(food.best_before)),
+                            );
+                            ui.end_row();
+                        }
+                    });
+            });
+    }
+
+    /// Show the long stored foods of all the inventories, the oldest on top
+    fn ageing_window(&mut self, ctx: &egui::Context) {
+        let config = config::current();
+        egui::Window::new("Ageing")
+            .open(&mut self.show_ageing)
+            .collapsible(false)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "Foods added at least {} days ago, whose best before is still far away",
+                    config.ageing.long_stored_days
+                ));
+                let foods = backend::long_stored_foods();
+                if foods.is_empty() {
+                    ui.label("Nothing forgotten");
+                    return;
+                }
+                egui::Grid::new("ageing grid")
+                    .num_columns(5)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for header in ["Food", "Inventory", "Location", "Age", "Best before"] {
+                            ui.label(egui::RichText::new(header).strong());
+                        }
+                        ui.end_row();
+
+                        for (inventory, food) in foods {
+                            ui.label(&food.name);
+                            ui.label(inventory);
+                            ui.label(food.location.as_deref().unwrap_or_default());
+                            let added_on = food.added_on.map(|day| {
+                                format!(
+                                    "Added on {}",
+                                    BestBefore::from(day).format(&config.date_format)
+                                )
+                            });
+                            ui.label(format_age(food.age()))
+                                .on_hover_text(added_on.unwrap_or_default());
+                            ui.label(
+                                egui::RichText::new(food.best_before.format(&config.date_format))
+                                    .color(state_color(food.best_before)),
+                            );
+                            ui.end_row();
+                        }
+                    });
+            });
+    }
+
+    /// Open the [`SettingsWindow`], unless already open
+    #[inline]
+    fn open_settings(&mut self) {
+        if self.settings.is_none() {
+            self.settings = Some(SettingsWindow::default());
+        }
+    }
+
+    /// Show the [`SettingsWindow`], if open
+    fn settings_window(&mut self, ctx: &egui::Context) {
+        if let Some(window) = &mut self.settings {
+            if !window.show(ctx) {
+                self.settings = None;
+            }
+        }
+    }
+
+    /// Open the [`DiagnosticsWindow`], or close it if already open
+    #[inline]
+    fn toggle_diagnostics(&mut self) {
+        self.diagnostics = match self.diagnostics {
+            Some(_) => None,
+            None => Some(DiagnosticsWindow::new()),
+        };
+    }
+
+    /// Show the [`DiagnosticsWindow`], if open
+    fn diagnostics_window(&mut self, ctx: &egui::Context) {
+        if let Some(window) = &mut self.diagnostics {
+            if !window.show(ctx) {
+                self.diagnostics = None;
+            }
+        }
+    }
+
+    /// Open the [`IntakeWindow`], or close it if already open
+    #[inline]
+    fn toggle_intake(&mut self) {
+        self.intake = match self.intake {
+            Some(_) => None,
+            None => Some(IntakeWindow::new()),
+        };
+    }
+
+    /// Show the [`IntakeWindow`], if open
+    fn intake_window(&mut self, ctx: &egui::Context) {
+        if let Some(window) = &mut self.intake {
+            if !window.show(ctx) {
+                self.intake = None;
+            }
+        }
+    }
+
+    /// Open the [`WasteWindow`], or close it if already open
+    #[inline]
+    fn toggle_waste(&mut self) {
+        self.waste = match self.waste {
+            Some(_) => None,
+            None => Some(WasteWindow::new()),
+        };
+    }
+
+    /// Show the [`WasteWindow`], if open
+    fn waste_window(&mut self, ctx: &egui::Context) {
+        if let Some(window) = &mut self.waste {
+            if !window.show(ctx) {
+                self.waste = None;
+            }
+        }
+    }
+
+    /// Resize the window when its size has been changed in the settings
+    fn apply_window_size(&mut self, frame: &mut eframe::Frame) {
+        let config = config::current();
+        let size = egui::vec2(config.window.width, config.window.height);
+        if size != self.window_size {
+            frame.set_window_size(size);
+            self.window_size = size;
+        }
     }
 
     /// Add a separator with some space on top and bottom
@@ -53,160 +490,395 @@ impl App {
         ui.separator();
         ui.add_space(7.0);
     }
+
+    /// Handle the global keyboard shortcuts. They are disabled while a text field has the
+    /// keyboard focus, so that typing a name does not trigger them
+    fn handle_shortcuts(&mut self, ctx: &egui::Context) {
+        if ctx.memory().focus().is_some() || self.is_window_open() {
+            return;
+        }
+
+        let input = ctx.input();
+        let typed = |text: &str| {
+            input
+                .events
+                .iter()
+                .any(|event| matches!(event, egui::Event::Text(t) if t == text))
+        };
+        let new_food = input.key_pressed(egui::Key::N);
+        let search = typed("/");
+        let help = typed("?") || input.key_pressed(egui::Key::F1);
+        let up = input.key_pressed(egui::Key::ArrowUp);
+        let down = input.key_pressed(egui::Key::ArrowDown);
+        let open_or_eat = input.key_pressed(egui::Key::Enter);
+        let edit = input.key_pressed(egui::Key::E);
+        let discard = input.key_pressed(egui::Key::Delete);
+        let settings = input.key_pressed(egui::Key::S);
+        let diagnostics = input.key_pressed(egui::Key::D);
+        let intake = input.key_pressed(egui::Key::I);
+        let waste = input.key_pressed(egui::Key::W);
+        let ageing = input.key_pressed(egui::Key::A);
+        let mute = input.key_pressed(egui::Key::M);
+        let escape = input.key_pressed(egui::Key::Escape);
+        drop(input);
+
+        if help {
+            self.show_shortcuts = !self.show_shortcuts;
+        } else if escape {
+            self.show_shortcuts = false;
+        }
+        // Only the help itself can be toggled while it is shown
+        if self.show_shortcuts {
+            return;
+        }
+        if new_food {
+            self.add_food_menu.open();
+        }
+        if search {
+            self.table.focus_search();
+        }
+        if up || down {
+            self.table.move_selection(down);
+        }
+        if open_or_eat {
+            self.table.open_or_eat_selected();
+        }
+        if edit {
+            self.table.edit_selected();
+        }
+        if discard {
+            self.table.discard_selected();
+        }
+        if settings {
+            self.open_settings();
+        }
+        if diagnostics {
+            self.toggle_diagnostics();
+        }
+        if intake {
+            self.toggle_intake();
+        }
+        if waste {
+            self.toggle_waste();
+        }
+        if ageing {
+            self.show_ageing = !self.show_ageing;
+        }
+        if mute {
+            let mut config = Config::clone(&config::current());
+            config.sound.enabled = !config.sound.enabled;
+            config::save(config);
+        }
+    }
+
+    /// Returns whether a window is shown over the table, apart from the keyboard shortcuts
+    /// help. The shortcuts act on the table, so they are off until the window is closed
+    fn is_window_open(&self) -> bool {
+        self.table.is_editing()
+            || self.settings.is_some()
+            || self.diagnostics.is_some()
+            || self.intake.is_some()
+            || self.waste.is_some()
+            || self.alerts.is_some()
+            || self.show_all_inventories
+            || self.show_ageing
+    }
+
+    /// Show the keyboard shortcuts help, if requested
+    fn shortcuts_window(&mut self, ctx: &egui::Context) {
+        egui::Window::new("Keyboard shortcuts")
+            .open(&mut self.show_shortcuts)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                egui::Grid::new("shortcuts grid")
+                    .num_columns(2)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for (keys, description) in Self::SHORTCUTS {
+                            ui.label(egui::RichText::new(keys).strong());
+                            ui.label(description);
+                            ui.end_row();
+                        }
+                    });
+            });
+    }
 }
 
 /// The [`AddFoodMenu`] lets user insert a new food in the [`Fridge`].
 pub struct AddFoodMenu {
     new_food_name: String,
-    new_day: u8,
-    new_month: u8,
+    new_barcode: String,
+    new_category: String,
+    new_location: String,
+    new_store: String,
+
+    /// Price of each copy, in the currency of the settings, 0 when unknown
+    price: f32,
+
+    /// Message explaining the outcome of the last barcode lookup
+    barcode_status: Option<String>,
+
+    date: DateInput,
+    nutrition: NutritionInput,
 
     /// This field defines how many copies of the new [`Food`] should be inserted
     /// in the [`Fridge`].
     quantity: u8,
+
+    /// Foods added in the past, used for autocompletion and learned defaults
+    known_foods: KnownFoods,
+
+    /// Path the known foods have been read from
+    known_foods_path: String,
+
+    /// true when the menu should be expanded at the next frame
+    open_requested: bool,
+
+    /// true when the name field should grab the keyboard focus
+    focus_name: bool,
+
+    /// true when the [`LeftoverForm`] is shown instead of the fields of a new food
+    leftover_mode: bool,
+
+    leftover: LeftoverForm,
 }
 
 impl Default for AddFoodMenu {
     fn default() -> Self {
-        let today = BestBefore::today();
         Self {
             new_food_name: String::new(),
-            new_day: today.day,
-            new_month: today.month,
+            new_barcode: String::new(),
+            new_category: String::new(),
+            new_location: String::new(),
+            new_store: String::new(),
+            price: 0.0,
+            barcode_status: None,
+            date: DateInput::new(BestBefore::today()),
+            nutrition: NutritionInput::default(),
             quantity: 1,
+            known_foods: KnownFoods::open(),
+            known_foods_path: config::current().paths.known_foods.clone(),
+            open_requested: false,
+            focus_name: false,
+            leftover_mode: false,
+            leftover: LeftoverForm::default(),
         }
     }
 }
 
 impl AddFoodMenu {
-    const FONT_SIZE: f32 = 18.0;
+    /// Expand the [`AddFoodMenu`] and move the keyboard focus to the name field
+    #[inline]
+    pub fn open(&mut self) {
+        self.open_requested = true;
+        self.focus_name = true;
+    }
 
     /// Render the[`AddFoodMenu`]
     pub fn ui(&mut self, ui: &mut egui::Ui) {
-        ui.horizontal(|ui| {
-            ui.collapsing(
-                egui::RichText::new("Add food")
-                    .strong()
-                    .heading()
-                    .font(egui::FontId::new(
-                        Table::HEADER_FONT_SIZE - 2.0,
-                        egui::FontFamily::Proportional,
-                    )),
-                |ui| {
-                    egui::Grid::new("add food menu grid").show(ui, |ui| {
-                        self.set_default_font(ui);
-                        ui.end_row();
-                        ui.vertical(|ui| {
-                            
-                            // Food name field
-                            ui.horizontal(|ui| {
-                                ui.add(
-                                    egui::widgets::TextEdit::singleline(&mut self.new_food_name)
-                                        .text_color(egui::color::Color32::WHITE)
-                                        .hint_text(egui::WidgetText::RichText(
-                                            egui::RichText::new("Name")
-                                                .strong()
-                                                .color(egui::Color32::GRAY),
-                                        )),
-                                );
-                            });
+        let config = config::current();
+        if config.paths.known_foods != self.known_foods_path {
+            // The known foods file has been moved in the settings
+            self.known_foods = KnownFoods::open();
+            self.known_foods_path = config.paths.known_foods.clone();
+        }
+        let max_quantity = config.add_food.max_quantity.max(1);
+        self.quantity = self.quantity.clamp(1, max_quantity);
+
+        egui::CollapsingHeader::new(egui::RichText::new("Add food").strong().heading().font(
+            egui::FontId::new(
+                config.appearance.header_font_size - 2.0,
+                egui::FontFamily::Proportional,
+            ),
+        ))
+        .open(mem::take(&mut self.open_requested).then_some(true))
+        .show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut self.leftover_mode, false, "Food");
+                ui.selectable_value(&mut self.leftover_mode, true, "Leftover");
+            });
+            if self.leftover_mode {
+                if self.leftover.ui(ui) {
+                    self.add_leftover();
+                }
+                return;
+            }
+
+            // Pressing Enter in any field but the barcode one adds the food
+            let mut submitted = false;
+            egui::Grid::new("add food menu grid").show(ui, |ui| {
+                self.set_default_font(ui);
+                ui.end_row();
+                ui.vertical(|ui| {
+                    // Barcode field. Keyboard-wedge scanners type the digits
+                    // and then press Enter, which triggers the lookup
+                    ui.horizontal(|ui| {
+                        let barcode_field = ui.add(
+                            egui::widgets::TextEdit::singleline(&mut self.new_barcode)
+                                .text_color(egui::color::Color32::WHITE)
+                                .hint_text(Self::hint_text("Barcode")),
+                        );
+                        if barcode_field.lost_focus() && ui.input().key_pressed(egui::Key::Enter) {
+                            self.look_up_barcode();
+                        }
+                        if let Some(status) = &self.barcode_status {
+                            ui.label(egui::RichText::new(status).color(egui::Color32::GRAY));
+                        }
+                    });
+
+                    // Food name field
+                    ui.horizontal(|ui| {
+                        let name_field = ui.add(
+                            egui::widgets::TextEdit::singleline(&mut self.new_food_name)
+                                .text_color(egui::color::Color32::WHITE)
+                                .hint_text(Self::hint_text("Name")),
+                        );
+                        if mem::take(&mut self.focus_name) {
+                            name_field.request_focus();
+                        }
+                        submitted |= submitted_with_enter(ui, &name_field);
+                    });
+
+                    // Name suggestions from current and past foods. Choosing one
+                    // prefills the other fields with its learned defaults
+                    let suggestions = self.known_foods.complete(
+                        &self.new_food_name,
+                        Fridge::open().into_iter().map(|food| food.name),
+                    );
+                    let chosen = matches!(
+                        suggestions.as_slice(),
+                        [name] if name.eq_ignore_ascii_case(self.new_food_name.trim())
+                    );
+                    if !suggestions.is_empty() && !chosen {
+                        ui.horizontal_wrapped(|ui| {
+                            for name in &suggestions {
+                                if ui.small_button(name.as_str()).clicked() {
+                                    self.choose_known_food(name);
+                                }
+                            }
                         });
-                        ui.end_row();
+                    }
 
-                        ui.horizontal(|ui| {
-                            let enabled = self.should_add_food_to_fridge();
-                            // add_enabled_sized was written by me because it wasn't included
-                            // in the egui library. The function adds a widget with some size
-                            // and with the flag of being enabled or not.
-                            let ok_button = ui.add_enabled_sized(
-                                enabled,
-                                [62.0; 2],
-                                egui::widgets::Button::new(
-                                    egui::RichText::new("Ok")
-                                        .strong()
-                                        .color(if enabled {
-                                            egui::Color32::WHITE
-                                        } else {
-                                            egui::Color32::GRAY
-                                        })
-                                        .size(Self::FONT_SIZE),
-                                ),
-                            );
-                            ui.end_row();
+                    // Category field
+                    ui.horizontal(|ui| {
+                        let field = ui.add(
+                            egui::widgets::TextEdit::singleline(&mut self.new_category)
+                                .text_color(egui::color::Color32::WHITE)
+                                .hint_text(Self::hint_text("Category")),
+                        );
+                        submitted |= submitted_with_enter(ui, &field);
+                    });
+
+                    // Location field
+                    ui.horizontal(|ui| {
+                        let field = ui.add(
+                            egui::widgets::TextEdit::singleline(&mut self.new_location)
+                                .text_color(egui::color::Color32::WHITE)
+                                .hint_text(Self::hint_text("Location")),
+                        );
+                        submitted |= submitted_with_enter(ui, &field);
+                    });
+
+                    // Store field
+                    ui.horizontal(|ui| {
+                        let field = ui.add(
+                            egui::widgets::TextEdit::singleline(&mut self.new_store)
+                                .text_color(egui::color::Color32::WHITE)
+                                .hint_text(Self::hint_text("Store")),
+                        );
+                        submitted |= submitted_with_enter(ui, &field);
+                    });
+
+                    self.nutrition.ui(ui);
+                });
+                ui.end_row();
+
+                ui.horizontal(|ui| {
+                    let enabled = self.should_add_food_to_fridge();
+                    // add_enabled_sized was written by me because it wasn't included
+                    // in the egui library. The function adds a widget with some size
+                    // and with the flag of being enabled or not.
+                    let ok_button = ui.add_enabled_sized(
+                        enabled,
+                        [62.0; 2],
+                        egui::widgets::Button::new(
+                            egui::RichText::new("Ok")
+                                .strong()
+                                .color(if enabled {
+                                    egui::Color32::WHITE
+                                } else {
+                                    egui::Color32::GRAY
+                                })
+                                .size(config.appearance.menu_font_size),
+                        ),
+                    );
+                    ui.end_row();
 
-                            if ok_button.clicked() {
-                                self.capitalize_new_food_name();
-                                for _ in 0..self.quantity {
-                                    let food = Food::new(
-                                        self.new_food_name.clone(),
-                                        self.new_day,
-                                        self.new_month,
-                                    );
-                                    // This way we reset the id and foods are unique
-                                    Fridge::open().add(food).update();
+                    if ok_button.clicked() {
+                        self.add_food();
+                    }
+                    ui.add_space(2.6);
+
+                    ui.vertical(|ui| {
+                        // Best before section: calendar and quick buttons
+                        ui.horizontal(|ui| {
+                            ui.add(Self::field_label("Date    "));
+                            ui.add_space(4.0);
+                            self.date.calendar_ui(ui);
+                            for (text, best_before) in [
+                                ("+3 days", BestBefore::in_days(3)),
+                                ("+1 week", BestBefore::in_days(7)),
+                                ("+1 month", BestBefore::in_months(1)),
+                            ] {
+                                if ui.small_button(text).clicked() {
+                                    self.date.set(best_before);
                                 }
-                                self.reset_fields();
                             }
-                            ui.add_space(2.6);
-
-                            ui.vertical(|ui| {
-
-                                // Day section
-                                ui.horizontal(|ui| {
-                                    let (label, drag_value) = new_label_and_drag_value!(
-                                        "Day     ",
-                                        &mut self.new_day,
-                                        1_u8..=31_u8
-                                    );
-                                    ui.add(label);
-                                    ui.add_space(4.0);
-                                    ui.add(drag_value);
-                                });
-                                
-                                // Month section
-                                ui.horizontal(|ui| {
-                                    let (label, drag_value) = new_label_and_drag_value!(
-                                        "Month  ",
-                                        &mut self.new_month,
-                                        1_u8..=12_u8
-                                    );
-                                    ui.add(label);
-                                    ui.add_space(7.0);
-                                    ui.add(drag_value);
-                                });
-                            
-                                // Quantity section
-                                ui.horizontal(|ui| {
-                                    let (label, drag_value) = new_label_and_drag_value!(
-                                        "Quantity",
-                                        &mut self.quantity,
-                                        1_u8..=10_u8
-                                    );
-                                    ui.add(label);
-                                    ui.add_space(0.3);
-                                    ui.add(drag_value);
-                                });
-                            });
+                        });
+
+                        // Best before typed as text, validated as you type
+                        ui.horizontal(|ui| {
+                            let date_field = self.date.text_ui(ui);
+                            submitted |= submitted_with_enter(ui, &date_field);
+                        });
+
+                        // Quantity section
+                        ui.horizontal(|ui| {
+                            let (label, drag_value) = new_label_and_drag_value!(
+                                "Quantity",
+                                &mut self.quantity,
+                                1_u8..=max_quantity
+                            );
+                            ui.add(label);
+                            ui.add_space(0.3);
+                            ui.add(drag_value);
+                        });
+
+                        // Price section
+                        ui.horizontal(|ui| {
+                            let (label, drag_value) = new_label_and_drag_value!(
+                                "Price   ",
+                                &mut self.price,
+                                0.0..=10_000.0
+                            );
+                            ui.add(label);
+                            ui.add_space(0.3);
+                            ui.add(
+                                drag_value
+                                    .max_decimals(2)
+                                    .suffix(format!(" {}", config.currency)),
+                            )
+                            .on_hover_text("Price of each copy");
                         });
                     });
-                },
-            );
-
-            // Display the today date
-            ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
-                ui.add_space(20.0);
-                let today = today();
-                let (day, month) = (today.day(), today.month());
-                ui.add(egui::Label::new(
-                    egui::RichText::new(format!("{} / {}", day, month))
-                        .strong()
-                        .heading()
-                        .font(egui::FontId::new(
-                            Table::HEADER_FONT_SIZE - 2.0,
-                            egui::FontFamily::Proportional,
-                        )),
-                ));
+                });
             });
+
+            if submitted && self.should_add_food_to_fridge() {
+                self.add_food();
+            }
         });
     }
 
@@ -214,17 +886,193 @@ impl AddFoodMenu {
     #[inline]
     fn set_default_font(&self, ui: &mut egui::Ui) {
         ui.style_mut().override_font_id = Some(egui::FontId::new(
-            Self::FONT_SIZE,
+            config::current().appearance.menu_font_size,
             egui::FontFamily::Proportional,
         ))
     }
 
+    /// Label in front of a field, matching the ones of [`new_label_and_drag_value!`]
+    #[inline]
+    fn field_label(text: &str) -> egui::Label {
+        egui::Label::new(egui::WidgetText::RichText(
+            egui::RichText::new(text)
+                .strong()
+                .color(egui::Color32::LIGHT_GRAY),
+        ))
+    }
+
+    /// Gray hint text for the text fields
+    #[inline]
+    fn hint_text(text: &str) -> egui::WidgetText {
+        egui::WidgetText::RichText(
+            egui::RichText::new(text)
+                .strong()
+                .color(egui::Color32::GRAY),
+        )
+    }
+
+    /// The barcode field, if it contains a valid barcode
+    #[inline]
+    fn barcode(&self) -> Option<String> {
+        let barcode = products::normalize(&self.new_barcode);
+        products::is_valid_barcode(&barcode).then_some(barcode)
+    }
+
+    /// The category field, if not empty
+    #[inline]
+    fn category(&self) -> Option<String> {
+        let category = self.new_category.trim();
+        (!category.is_empty()).then(|| category.to_string())
+    }
+
+    /// The location field, if not empty
+    #[inline]
+    fn location(&self) -> Option<String> {
+        let location = self.new_location.trim();
+        (!location.is_empty()).then(|| location.to_string())
+    }
+
+    /// The store field, if not empty
+    #[inline]
+    fn store(&self) -> Option<String> {
+        let store = self.new_store.trim();
+        (!store.is_empty()).then(|| store.to_string())
+    }
+
+    /// Fill the fields with the learned defaults of a known food: the best before becomes
+    /// today plus its typical shelf life
+    fn choose_known_food(&mut self, name: &str) {
+        self.new_food_name = name.to_string();
+        let Some(known) = self.known_foods.get(name) else {
+            return;
+        };
+
+        let best_before = BestBefore::in_days(known.shelf_life);
+        self.new_category = known.category.clone().unwrap_or_default();
+        self.new_location = known.location.clone().unwrap_or_default();
+        self.quantity = known
+            .quantity
+            .clamp(1, config::current().add_food.max_quantity.max(1));
+        self.date.set(best_before);
+    }
+
+    /// Add as many copies of the new [`Food`] as requested to the [`Fridge`], learning
+    /// from it, and reset the fields
+    fn add_food(&mut self) {
+        self.capitalize_new_food_name();
+        let food = Food::new(self.new_food_name.clone(), self.date.best_before())
+            .with_barcode(self.barcode())
+            .with_category(self.category())
+            .with_location(self.location())
+            .with_nutrition(self.nutrition.value())
+            .with_price(to_cents(self.price))
+            .with_store(self.store())
+            .added_today();
+        // Each copy gets its own id from the fridge
+        let mut fridge = Fridge::open();
+        for _ in 0..self.quantity {
+            fridge = fridge.add(food.clone());
+        }
+        fridge.update();
+        sound::play(SoundEvent::Added);
+        self.learn_product();
+        self.learn_known_food();
+        self.reset_fields();
+    }
+
+    /// Add the leftover of the [`LeftoverForm`] to the [`Fridge`], removing the
+    /// ingredients used up to cook it, and reset the form
+    fn add_leftover(&mut self) {
+        let fridge = Fridge::open();
+        let used = fridge
+            .iter()
+            .filter(|food| self.leftover.ingredients.contains(&food.id))
+            .cloned()
+            .collect::<Vec<_>>();
+        let food = Food::leftover(
+            self.leftover.name(),
+            Leftover {
+                cooked_on: self.leftover.cooked_on.best_before().date(),
+                dish: self.leftover.dish.clone(),
+                ingredients: used
+                    .iter()
+                    .map(|food| Ingredient {
+                        id: food.id,
+                        name: food.name.clone(),
+                    })
+                    .collect(),
+            },
+        )
+        .added_today();
+
+        let (fridge, leftover) = fridge.add_with_id(food);
+        let fridge = used.iter().try_fold(fridge, |fridge, ingredient| {
+            fridge.use_in(ingredient.id, leftover)
+        });
+        if update_fridge(fridge) {
+            sound::play(SoundEvent::Added);
+        }
+        self.leftover = LeftoverForm::default();
+    }
+
+    /// Remember the food being added, for autocompletion and learned defaults
+    fn learn_known_food(&mut self) {
+        let shelf_life = self.date.best_before().days_left();
+        self.known_foods = mem::take(&mut self.known_foods).learn(
+            &self.new_food_name,
+            self.category(),
+            self.location(),
+            self.quantity,
+            shelf_life,
+        );
+        self.known_foods.update();
+    }
+
+    /// Look up the barcode in the [`ProductDatabase`] and prefill name, category and
+    /// best before date with what we know about the product
+    fn look_up_barcode(&mut self) {
+        let Some(barcode) = self.barcode() else {
+            self.barcode_status = Some("Invalid barcode".to_string());
+            return;
+        };
+
+        match ProductDatabase::open().lookup(&barcode) {
+            Some(product) => {
+                self.new_food_name = product.name.clone();
+                self.new_category = product.category.clone().unwrap_or_default();
+                if let Some(shelf_life) = product.shelf_life {
+                    self.date.set(BestBefore::in_days(shelf_life));
+                }
+                self.nutrition = NutritionInput::new(product.nutrition.clone());
+                self.barcode_status = None;
+            }
+            None => self.barcode_status = Some("Unknown product".to_string()),
+        }
+    }
+
+    /// Remember the product being added, so that next time its barcode prefills the fields.
+    /// The shelf life is the distance between today and the chosen best before date
+    fn learn_product(&self) {
+        let Some(barcode) = self.barcode() else {
+            return;
+        };
+
+        let product = Product {
+            name: self.new_food_name.clone(),
+            category: self.category(),
+            shelf_life: Some(self.date.best_before().days_left()),
+            nutrition: self.nutrition.value(),
+            learned: true,
+        };
+        ProductDatabase::open().learn(&barcode, product).update();
+    }
+
     /// We add the food to the fridge if
     #[inline]
     fn should_add_food_to_fridge(&self) -> bool {
         matches!(
             self.new_food_name.chars().next(),
-            Some(ch) if ch.is_ascii() && BestBefore::would_be_valid(self.new_day, self.new_month)
+            Some(ch) if ch.is_ascii() && self.date.is_valid()
         )
     }
 
@@ -241,25 +1089,505 @@ impl AddFoodMenu {
     #[inline]
     fn reset_fields(&mut self) {
         self.new_food_name.clear();
+        self.new_barcode.clear();
+        self.new_category.clear();
+        self.new_location.clear();
+        self.new_store.clear();
+        self.price = 0.0;
+        self.barcode_status = None;
+        self.nutrition = NutritionInput::default();
         self.quantity = 1;
     }
+
+    /// The day changed: a best before left at the former today moves to the new one
+    pub fn roll_over(&mut self, yesterday: chrono::NaiveDate) {
+        for date in [&mut self.date, &mut self.leftover.cooked_on] {
+            if date.best_before() == BestBefore::from(yesterday) {
+                date.set(BestBefore::today());
+            }
+        }
+    }
+}
+
+/// The [`LeftoverForm`] quickly adds a leftover of a dish cooked at home. Its best before
+/// date is derived from the day it was cooked and the shelf life of its dish, and the
+/// [`Food`]s used up to cook it can be picked from the inventory
+pub struct LeftoverForm {
+    /// The type of dish when empty
+    name: String,
+
+    /// Type of dish, one of the [`Leftovers`](config::Leftovers) of the settings, or empty
+    /// for the other dishes
+    dish: String,
+
+    /// The day the dish was cooked, edited as a [`BestBefore`]
+    cooked_on: DateInput,
+
+    /// Ids of the [`Food`]s of the inventory used up to cook it
+    ingredients: BTreeSet<FoodId>,
+}
+
+impl Default for LeftoverForm {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            dish: String::new(),
+            cooked_on: DateInput::new(BestBefore::today()),
+            ingredients: BTreeSet::new(),
+        }
+    }
+}
+
+impl LeftoverForm {
+    /// Render the [`LeftoverForm`]. Return whether the leftover should be added
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let config = config::current();
+        let leftovers = &config.leftovers;
+        let mut submitted = false;
+        egui::Grid::new("leftover grid")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.add(AddFoodMenu::field_label("Name"));
+                let field = ui.add(
+                    egui::widgets::TextEdit::singleline(&mut self.name)
+                        .text_color(egui::color::Color32::WHITE)
+                        .hint_text(AddFoodMenu::hint_text("The dish when empty")),
+                );
+                submitted |= submitted_with_enter(ui, &field);
+                ui.end_row();
+
+                ui.add(AddFoodMenu::field_label("Dish"));
+                let days = |dish: &str, days: u16| {
+                    format!(
+                        "{} ({} day{})",
+                        dish,
+                        days,
+                        if days == 1 { "" } else { "s" }
+                    )
+                };
+                egui::ComboBox::from_id_source("leftover dish")
+                    .selected_text(days(
+                        if self.dish.is_empty() {
+                            "Other"
+                        } else {
+                            &self.dish
+                        },
+                        leftovers.shelf_life(&self.dish),
+                    ))
+                    .show_ui(ui, |ui| {
+                        for (dish, shelf_life) in &leftovers.dishes {
+                            ui.selectable_value(
+                                &mut self.dish,
+                                dish.clone(),
+                                days(dish, *shelf_life),
+                            );
+                        }
+                        ui.selectable_value(
+                            &mut self.dish,
+                            String::new(),
+                            days("Other", leftovers.default_days),
+                        );
+                    });
+                ui.end_row();
+
+                ui.add(AddFoodMenu::field_label("Cooked on"));
+                ui.horizontal(|ui| {
+                    self.cooked_on.calendar_ui(ui);
+                    for (text, days_ago) in [("Today", 0), ("Yesterday", 1)] {
+                        if ui.small_button(text).clicked() {
+                            let day = clock::today() - chrono::Duration::days(days_ago);
+                            self.cooked_on.set(BestBefore::from(day));
+                        }
+                    }
+                });
+                ui.end_row();
+
+                ui.label("");
+                let field = self.cooked_on.text_ui(ui);
+                submitted |= submitted_with_enter(ui, &field);
+                ui.end_row();
+
+                ui.add(AddFoodMenu::field_label("Best before"));
+                if self.is_cooked_on_valid() {
+                    let best_before = Leftover::best_before(self.cooked_on_date(), &self.dish);
+                    ui.label(
+                        egui::RichText::new(best_before.format(&config.date_format))
+                            .color(state_color(best_before)),
+                    );
+                } else {
+                    ui.label("The dish must have been cooked by today");
+                }
+                ui.end_row();
+
+                ui.add(AddFoodMenu::field_label("Made with"));
+                ui.vertical(|ui| {
+                    let fridge = Fridge::open();
+                    if fridge.is_empty() {
+                        ui.label("Nothing in the inventory");
+                    }
+                    egui::ScrollArea::vertical()
+                        .id_source("leftover ingredients")
+                        .max_height(100.0)
+                        .show(ui, |ui| {
+                            for food in fridge.iter() {
+                                let mut used = self.ingredients.contains(&food.id);
+                                if ui.checkbox(&mut used, &food.name).changed() {
+                                    if used {
+                                        self.ingredients.insert(food.id);
+                                    } else {
+                                        self.ingredients.remove(&food.id);
+                                    }
+                                }
+                            }
+                        });
+                });
+                ui.end_row();
+            });
+
+        let valid = self.is_valid();
+        submitted |= ui
+            .add_enabled(valid, egui::widgets::Button::new("Add leftover"))
+            .on_hover_text("The foods it is made with are removed from the inventory")
+            .clicked();
+        submitted && valid
+    }
+
+    /// Name of the leftover: the one typed, or else the dish, capitalized
+    fn name(&self) -> String {
+        let name = match self.name.trim() {
+            "" if self.dish.is_empty() => "Leftover",
+            "" => self.dish.trim(),
+            name => name,
+        };
+        let mut chars = name.chars();
+        chars
+            .next()
+            .map(|first| first.to_uppercase().chain(chars).collect())
+            .unwrap_or_default()
+    }
+
+    /// The day the dish was cooked
+    #[inline]
+    fn cooked_on_date(&self) -> chrono::NaiveDate {
+        self.cooked_on.best_before().date()
+    }
+
+    /// Returns whether the day the dish was cooked is valid and not in the future
+    #[inline]
+    fn is_cooked_on_valid(&self) -> bool {
+        self.cooked_on.is_valid() && self.cooked_on_date() <= clock::today()
+    }
+
+    /// Returns whether the leftover can be added
+    #[inline]
+    fn is_valid(&self) -> bool {
+        self.is_cooked_on_valid()
+    }
+}
+
+/// The [`DateInput`] edits a [`BestBefore`], either picking it with the [`Calendar`]
+/// or typing it in a text field.
+pub struct DateInput {
+    best_before: BestBefore,
+
+    /// Best before typed by the user, see [`BestBefore::parse`] for the accepted formats
+    text: String,
+
+    /// Message explaining why the typed best before has been rejected
+    error: Option<String>,
+
+    calendar: Calendar,
+}
+
+impl DateInput {
+    const TEXT_FIELD_WIDTH: f32 = 160.0;
+
+    #[inline]
+    pub fn new(best_before: BestBefore) -> Self {
+        Self {
+            best_before,
+            text: String::new(),
+            error: None,
+            calendar: Calendar::default(),
+        }
+    }
+
+    #[inline]
+    pub fn best_before(&self) -> BestBefore {
+        self.best_before
+    }
+
+    /// Returns whether the typed best before, if any, has been accepted
+    #[inline]
+    pub fn is_valid(&self) -> bool {
+        self.error.is_none()
+    }
+
+    /// Set the best before, discarding whatever was typed in the text field
+    #[inline]
+    pub fn set(&mut self, best_before: BestBefore) {
+        self.best_before = best_before;
+        self.text.clear();
+        self.error = None;
+    }
+
+    /// Render the [`Calendar`] button
+    pub fn calendar_ui(&mut self, ui: &mut egui::Ui) {
+        if self.calendar.ui(ui, &mut self.best_before) {
+            self.set(self.best_before);
+        }
+    }
+
+    /// Render the text field, validated as you type, followed by the validation message.
+    /// Return the [`egui::Response`] of the text field
+    pub fn text_ui(&mut self, ui: &mut egui::Ui) -> egui::Response {
+        let field = ui.add(
+            egui::widgets::TextEdit::singleline(&mut self.text)
+                .desired_width(Self::TEXT_FIELD_WIDTH)
+                .text_color(egui::color::Color32::WHITE)
+                .hint_text(AddFoodMenu::hint_text("12/03, 2026-03-12, +5d")),
+        );
+        if field.changed() {
+            self.parse_text();
+        }
+        if let Some(error) = &self.error {
+            ui.label(egui::RichText::new(error).color(egui::Color32::RED));
+        }
+        field
+    }
+
+    /// Parse the text field, explaining why it is rejected if needed
+    fn parse_text(&mut self) {
+        if self.text.trim().is_empty() {
+            self.error = None;
+            return;
+        }
+
+        match BestBefore::parse(&self.text) {
+            Ok(best_before) => {
+                self.best_before = best_before;
+                self.error = None;
+            }
+            Err(err) => self.error = Some(err.to_string()),
+        }
+    }
+}
+
+/// The [`Calendar`] is a date picker: a button showing the date, which opens a popup
+/// with the days of the month.
+#[derive(Default)]
+pub struct Calendar {
+    /// First day of the month shown in the popup, [`None`] when the popup is closed
+    shown_month: Option<chrono::NaiveDate>,
+}
+
+impl Calendar {
+    const WEEKDAYS: [&'static str; 7] = ["Mo", "Tu", "We", "Th", "Fr", "Sa", "Su"];
+
+    /// Render the [`Calendar`]. Return true when the user picked a day
+    pub fn ui(&mut self, ui: &mut egui::Ui, best_before: &mut BestBefore) -> bool {
+        let button = ui.button(format!("{} / {}", best_before, best_before.year));
+        if button.clicked() {
+            self.shown_month = match self.shown_month {
+                Some(_) => None,
+                None => best_before.date().with_day(1),
+            };
+        }
+        let Some(mut shown_month) = self.shown_month else {
+            return false;
+        };
+
+        // Several calendars can be shown at once, such as in the add food menu and in the
+        // edit window, so their ids come from their button
+        let id = button.id;
+        let mut picked = false;
+        let popup = egui::Area::new(id.with("calendar popup"))
+            .order(egui::Order::Foreground)
+            .fixed_pos(button.rect.left_bottom())
+            .show(ui.ctx(), |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    // Month navigation
+                    ui.horizontal(|ui| {
+                        if ui.small_button("<").clicked() {
+                            shown_month = shown_month
+                                .checked_sub_months(chrono::Months::new(1))
+                                .unwrap_or(shown_month);
+                        }
+                        ui.label(shown_month.format("%B %Y").to_string());
+                        if ui.small_button(">").clicked() {
+                            shown_month = shown_month
+                                .checked_add_months(chrono::Months::new(1))
+                                .unwrap_or(shown_month);
+                        }
+                    });
+
+                    // Days of the month, weeks start on Monday
+                    egui::Grid::new(id.with("calendar grid")).show(ui, |ui| {
+                        for weekday in Self::WEEKDAYS {
+                            ui.label(egui::RichText::new(weekday).color(egui::Color32::GRAY));
+                        }
+                        ui.end_row();
+
+                        let offset = shown_month.weekday().num_days_from_monday();
+                        for _ in 0..offset {
+                            ui.label("");
+                        }
+                        let selected = best_before.date();
+                        for (column, date) in shown_month
+                            .iter_days()
+                            .take_while(|date| date.month() == shown_month.month())
+                            .enumerate()
+                        {
+                            let day = ui.selectable_label(date == selected, date.day().to_string());
+                            if day.clicked() {
+                                *best_before = BestBefore::from(date);
+                                picked = true;
+                            }
+                            if (column as u32 + offset) % 7 == 6 {
+                                ui.end_row();
+                            }
+                        }
+                    });
+                });
+            });
+
+        let closed = picked
+            || ui.input().key_pressed(egui::Key::Escape)
+            || (popup.response.clicked_elsewhere() && !button.clicked());
+        self.shown_month = (!closed).then_some(shown_month);
+        picked
+    }
+}
+
+/// The [`NutritionInput`] edits the nutrition facts of a [`Food`], which are optional
+#[derive(Default)]
+pub struct NutritionInput {
+    /// false when the [`Food`] has no nutrition facts
+    enabled: bool,
+
+    nutrition: Nutrition,
+
+    /// Weight of one unit in grams, 0 when unknown
+    unit_grams: f32,
+}
+
+impl NutritionInput {
+    #[inline]
+    pub fn new(nutrition: Option<Nutrition>) -> Self {
+        match nutrition {
+            Some(nutrition) => Self {
+                enabled: true,
+                unit_grams: nutrition.unit_grams.unwrap_or_default(),
+                nutrition,
+            },
+            None => Self::default(),
+        }
+    }
+
+    /// Render the [`NutritionInput`]: a checkbox, and the values once it is ticked
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.enabled, "Nutrition facts");
+        if !self.enabled {
+            return;
+        }
+
+        egui::Grid::new(ui.id().with("nutrition grid"))
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.add(AddFoodMenu::field_label("Per"));
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut self.nutrition.basis, Basis::Per100g, "100 g");
+                    ui.radio_value(&mut self.nutrition.basis, Basis::PerUnit, "unit");
+                });
+                ui.end_row();
+
+                let nutrients = &mut self.nutrition.nutrients;
+                for (text, value, suffix) in [
+                    ("Energy", &mut nutrients.kcal, " kcal"),
+                    ("Protein", &mut nutrients.protein, " g"),
+                    ("Carbs", &mut nutrients.carbs, " g"),
+                    ("Fat", &mut nutrients.fat, " g"),
+                ] {
+                    ui.add(AddFoodMenu::field_label(text));
+                    ui.add(Self::drag_value(value, suffix));
+                    ui.end_row();
+                }
+
+                if self.nutrition.basis == Basis::Per100g {
+                    ui.add(AddFoodMenu::field_label("Unit weight"));
+                    ui.add(Self::drag_value(&mut self.unit_grams, " g"))
+                        .on_hover_text("Left at 0, eating one unit counts as eating 100 g");
+                    ui.end_row();
+                }
+            });
+    }
+
+    /// Drag value of a nutrient or of the unit weight
+    #[inline]
+    fn drag_value<'a>(value: &'a mut f32, suffix: &str) -> egui::widgets::DragValue<'a> {
+        egui::widgets::DragValue::new(value)
+            .clamp_range(0.0..=10_000.0)
+            .max_decimals(1)
+            .suffix(suffix)
+    }
+
+    /// The nutrition facts, [`None`] when not ticked or not valid
+    pub fn value(&self) -> Option<Nutrition> {
+        let nutrition = Nutrition {
+            unit_grams: (self.nutrition.basis == Basis::Per100g && self.unit_grams > 0.0)
+                .then_some(self.unit_grams),
+            ..self.nutrition.clone()
+        };
+        (self.enabled && nutrition.is_valid()).then_some(nutrition)
+    }
 }
 
 /// The [`Table`] contains the information related to the single [`Food`] items.
 /// Each row is a [`Food`] element.
 #[derive(Default)]
-pub struct Table;
+pub struct Table {
+    /// Only the [`Food`]s whose name contains this text are shown
+    search: String,
+
+    /// true when the search field should grab the keyboard focus
+    focus_search: bool,
+
+    /// Id of the [`Food`] selected with the keyboard
+    selected: Option<FoodId>,
+
+    /// true when the selected row should be scrolled into view
+    scroll_to_selected: bool,
+
+    /// The [`EditFoodWindow`] of the [`Food`] being edited, if any
+    editing: Option<EditFoodWindow>,
+
+    /// true when the oldest [`Food`]s are on top, rather than the ones expiring first
+    sort_by_age: bool,
+}
 
 impl Table {
-    const BEST_BEFORE_COLUMN_WIDTH: f32 = 200.0;
-    const FOOD_EATEN_BUTTON_COLUMN_WIDTH: f32 = 137.0;
-    const ROW_HEIGHT: f32 = 26.0;
-    const HEADER_FONT_SIZE: f32 = 32.0;
     const HEADER_HEIGHT: f32 = 46.0;
-    const FONT_SIZE: f32 = 23.0;
 
     /// Render [`Table`]
-    pub fn ui(&self, ui: &mut egui::Ui) {
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        // Search field
+        let search_field = ui.add(
+            egui::widgets::TextEdit::singleline(&mut self.search)
+                .text_color(egui::color::Color32::WHITE)
+                .hint_text(AddFoodMenu::hint_text("Search")),
+        );
+        if mem::take(&mut self.focus_search) {
+            search_field.request_focus();
+        }
+        ui.add_space(4.0);
+
+        let config = config::current();
+        let appearance = &config.appearance;
+        let foods = self.visible_foods();
+        let selected = self.selected;
+        let scroll_to_selected = mem::take(&mut self.scroll_to_selected);
+        let mut clicked = None;
+        let mut sort_by_age = None;
         egui_extras::StripBuilder::new(ui)
             .size(egui_extras::Size::remainder())
             .vertical(|mut strip| {
@@ -268,14 +1596,19 @@ impl Table {
                         .striped(true)
                         .column(egui_extras::Size::remainder())
                         .column(
-                            egui_extras::Size::initial(Self::BEST_BEFORE_COLUMN_WIDTH)
-                                .at_least(Self::BEST_BEFORE_COLUMN_WIDTH)
-                                .at_most(Self::BEST_BEFORE_COLUMN_WIDTH),
+                            egui_extras::Size::initial(appearance.best_before_column_width)
+                                .at_least(appearance.best_before_column_width)
+                                .at_most(appearance.best_before_column_width),
+                        )
+                        .column(
+                            egui_extras::Size::initial(appearance.age_column_width)
+                                .at_least(appearance.age_column_width)
+                                .at_most(appearance.age_column_width),
                         )
                         .column(
-                            egui_extras::Size::initial(Self::FOOD_EATEN_BUTTON_COLUMN_WIDTH)
-                                .at_least(Self::FOOD_EATEN_BUTTON_COLUMN_WIDTH)
-                                .at_most(Self::FOOD_EATEN_BUTTON_COLUMN_WIDTH),
+                            egui_extras::Size::initial(appearance.button_column_width)
+                                .at_least(appearance.button_column_width)
+                                .at_most(appearance.button_column_width),
                         )
                         .header(Self::HEADER_HEIGHT, |mut header| {
                             header.col(|ui| {
@@ -284,28 +1617,77 @@ impl Table {
                                 });
                             });
                             header.col(|ui| {
-                                ui.add(self.header_label("Best before"));
+                                let text = self.sort_marker("Best before", false);
+                                if ui
+                                    .add(self.header_label(text).sense(egui::Sense::click()))
+                                    .on_hover_text("Sort by best before")
+                                    .clicked()
+                                {
+                                    sort_by_age = Some(false);
+                                }
+                            });
+                            header.col(|ui| {
+                                let text = self.sort_marker("Age", true);
+                                if ui
+                                    .add(self.header_label(text).sense(egui::Sense::click()))
+                                    .on_hover_text("Sort by age, the oldest first")
+                                    .clicked()
+                                {
+                                    sort_by_age = Some(true);
+                                }
                             });
                             header.col(|ui| {
                                 ui.add(egui::Label::new(String::new()));
                             });
                         })
                         .body(|mut body| {
-                            let fridge = Fridge::open();
-                            for mut food in fridge {
-                                body.row(Self::ROW_HEIGHT, |mut row| {
+                            for food in foods {
+                                let is_selected = selected == Some(food.id);
+                                body.row(appearance.row_height, |mut row| {
                                     row.col(|ui| {
-                                        ui.add(self.cell_label(&food.name));
+                                        self.highlight_if_selected(ui, is_selected);
+                                        let mut label = ui.add(self.cell_label(&food.name));
+                                        if let Some(leftover) = &food.leftover {
+                                            label = label.on_hover_text(describe_leftover(
+                                                leftover,
+                                                &config.date_format,
+                                            ));
+                                        }
+                                        if is_selected && scroll_to_selected {
+                                            label.scroll_to_me(Some(egui::Align::Center));
+                                        }
                                     });
                                     row.col(|ui| {
-                                        let color = egui::Color32::from(food.best_before);
+                                        self.highlight_if_selected(ui, is_selected);
+                                        let color = state_color(food.best_before);
                                         ui.vertical_centered_justified(|ui| {
                                             ui.add(self.cell_label_with_color(
-                                                food.best_before.to_string(),
+                                                food.best_before.format(&config.date_format),
                                                 color,
                                             ));
                                         });
                                     });
+                                    row.col(|ui| {
+                                        self.highlight_if_selected(ui, is_selected);
+                                        let color = if food.is_long_stored() {
+                                            egui::Color32::LIGHT_BLUE
+                                        } else {
+                                            egui::Color32::WHITE
+                                        };
+                                        ui.vertical_centered_justified(|ui| {
+                                            let label = ui.add(self.cell_label_with_color(
+                                                format_age(food.age()),
+                                                color,
+                                            ));
+                                            if let Some(day) = food.added_on {
+                                                label.on_hover_text(format!(
+                                                    "Added on {}",
+                                                    BestBefore::from(day)
+                                                        .format(&config.date_format)
+                                                ));
+                                            }
+                                        });
+                                    });
                                     row.col(|ui| {
                                         let button_text = if food.open { "Open" } else { "Eaten" };
 
@@ -313,14 +1695,14 @@ impl Table {
                                         if ui
                                             .add_sized(
                                                 (
-                                                    Self::FOOD_EATEN_BUTTON_COLUMN_WIDTH + 2.0,
-                                                    Self::ROW_HEIGHT,
+                                                    appearance.button_column_width + 2.0,
+                                                    appearance.row_height,
                                                 ),
                                                 egui::widgets::Button::new(
                                                     egui::RichText::new(button_text).font(
                                                         // Avoid borrow `*self` both mut and not mut
                                                         egui::FontId::new(
-                                                            Self::FONT_SIZE,
+                                                            appearance.font_size,
                                                             egui::FontFamily::Proportional,
                                                         ),
                                                     ),
@@ -328,16 +1710,7 @@ impl Table {
                                             )
                                             .clicked()
                                         {
-                                            // Remove food from fridge
-                                            Fridge::open().remove(&food).update();
-                                            if food.open {
-                                                play_eating_sound();
-                                            } else {
-                                                food.open = true;
-
-                                                // Add the food back but with the open state
-                                                Fridge::open().add(food).update();
-                                            }
+                                            clicked = Some(food);
                                         }
                                     });
                                 });
@@ -345,6 +1718,152 @@ impl Table {
                         });
                 })
             });
+
+        if let Some(sort_by_age) = sort_by_age {
+            self.sort_by_age = sort_by_age;
+        }
+        if let Some(food) = clicked {
+            self.selected = Some(food.id);
+            Self::open_or_eat(food);
+        }
+    }
+
+    /// Reload the [`Food`] being edited, because the inventory has been changed outside of
+    /// the app
+    #[inline]
+    pub fn reload(&mut self) {
+        if let Some(window) = &mut self.editing {
+            window.reload();
+        }
+    }
+
+    /// Forget the selected [`Food`] and close the [`EditFoodWindow`], because the
+    /// inventory has changed
+    #[inline]
+    pub fn reset_selection(&mut self) {
+        self.selected = None;
+        self.editing = None;
+    }
+
+    /// Move the keyboard focus to the search field
+    #[inline]
+    pub fn focus_search(&mut self) {
+        self.focus_search = true;
+    }
+
+    /// Returns whether a [`Food`] is being edited
+    #[inline]
+    pub fn is_editing(&self) -> bool {
+        self.editing.is_some()
+    }
+
+    /// Select the next or the previous visible [`Food`]. When nothing is selected,
+    /// the first one is
+    pub fn move_selection(&mut self, down: bool) {
+        let foods = self.visible_foods();
+        if foods.is_empty() {
+            self.selected = None;
+            return;
+        }
+
+        let current = self
+            .selected
+            .and_then(|id| foods.iter().position(|food| food.id == id));
+        let index = match current {
+            None => 0,
+            Some(i) if down => (i + 1).min(foods.len() - 1),
+            Some(i) => i.saturating_sub(1),
+        };
+        self.selected = Some(foods[index].id);
+        self.scroll_to_selected = true;
+    }
+
+    /// Open the selected [`Food`], or eat it if already open
+    #[inline]
+    pub fn open_or_eat_selected(&mut self) {
+        if let Some(food) = self.selected_food() {
+            Self::open_or_eat(food);
+        }
+    }
+
+    /// Ask whether to throw away the selected [`Food`] without eating it, in its
+    /// [`EditFoodWindow`]
+    #[inline]
+    pub fn discard_selected(&mut self) {
+        if let Some(food) = self.selected_food() {
+            let mut window = EditFoodWindow::new(food);
+            window.confirm_discard = true;
+            self.editing = Some(window);
+        }
+    }
+
+    /// Open the [`EditFoodWindow`] of the selected [`Food`]
+    #[inline]
+    pub fn edit_selected(&mut self) {
+        if let Some(food) = self.selected_food() {
+            self.editing = Some(EditFoodWindow::new(food));
+        }
+    }
+
+    /// Show the [`EditFoodWindow`] of the [`Food`] being edited, if any
+    pub fn edit_food_window(&mut self, ctx: &egui::Context) {
+        if let Some(window) = &mut self.editing {
+            if !window.show(ctx) {
+                self.editing = None;
+            }
+        }
+    }
+
+    /// The [`Food`]s matching the search, in the order they are shown
+    fn visible_foods(&self) -> Vec<Food> {
+        let search = self.search.trim().to_lowercase();
+        let mut foods = Fridge::open()
+            .into_iter()
+            .filter(|food| food.name.to_lowercase().contains(&search))
+            .collect::<Vec<_>>();
+        if self.sort_by_age {
+            // Stable, so foods of the same age stay sorted by best before. The ones of
+            // unknown age come last
+            foods.sort_by_key(|food| cmp::Reverse(food.age()));
+        }
+        foods
+    }
+
+    /// The selected [`Food`], if it is still visible
+    #[inline]
+    fn selected_food(&self) -> Option<Food> {
+        let id = self.selected?;
+        self.visible_foods().into_iter().find(|food| food.id == id)
+    }
+
+    /// Open a [`Food`], or eat it if already open
+    fn open_or_eat(food: Food) {
+        if food.open {
+            if update_fridge(Fridge::open().eat(food.id)) {
+                sound::play(SoundEvent::Eaten);
+            }
+        } else if update_fridge(Fridge::open().set_open(food.id, true)) {
+            sound::play(SoundEvent::Opened);
+        }
+    }
+
+    /// Paint the background of a cell of the selected row
+    #[inline]
+    fn highlight_if_selected(&self, ui: &mut egui::Ui, is_selected: bool) {
+        if is_selected {
+            let fill = ui.visuals().selection.bg_fill;
+            ui.painter().rect_filled(ui.max_rect(), 0.0, fill);
+        }
+    }
+
+    /// Text of a header, marked when the table is sorted by its column
+    #[inline]
+    fn sort_marker(&self, text: &str, by_age: bool) -> String {
+        if self.sort_by_age == by_age {
+            format!("{} ⏷", text)
+        } else {
+            text.to_string()
+        }
     }
 
     /// New header label with given text
@@ -356,7 +1875,7 @@ impl Table {
                 .heading()
                 .underline()
                 .font(egui::FontId::new(
-                    Self::HEADER_FONT_SIZE,
+                    config::current().appearance.header_font_size,
                     egui::FontFamily::Proportional,
                 )),
         )
@@ -394,21 +1913,297 @@ impl Table {
     /// Return the default font
     #[inline]
     fn default_font(&self) -> egui::FontId {
-        egui::FontId::new(Self::FONT_SIZE, egui::FontFamily::Proportional)
+        egui::FontId::new(
+            config::current().appearance.font_size,
+            egui::FontFamily::Proportional,
+        )
     }
 }
 
-/// Translate the [`BestBefore`] into a [`egui::Color32`]
-impl From<BestBefore> for egui::Color32 {
-    fn from(best_before: BestBefore) -> Self {
-        match best_before.state() {
-            FoodState::FarFromExpiring => Self::GREEN,
-            FoodState::CloseFromExpiring => Self::YELLOW,
-            FoodState::Expired => Self::RED,
+/// The [`EditFoodWindow`] lets the user change a [`Food`] already in the [`Fridge`].
+pub struct EditFoodWindow {
+    /// The [`Food`] as it was before editing
+    food: Food,
+    name: String,
+    date: DateInput,
+    category: String,
+    location: String,
+    store: String,
+
+    /// In the currency of the settings, 0 when unknown
+    price: f32,
+
+    nutrition: NutritionInput,
+
+    /// Warning shown when the [`Food`] has been changed outside of the app while edited
+    conflict: Option<String>,
+
+    /// true while asking whether to throw the [`Food`] away
+    confirm_discard: bool,
+}
+
+impl EditFoodWindow {
+    #[inline]
+    pub fn new(food: Food) -> Self {
+        Self {
+            name: food.name.clone(),
+            date: DateInput::new(food.best_before),
+            category: food.category.clone().unwrap_or_default(),
+            location: food.location.clone().unwrap_or_default(),
+            store: food.store.clone().unwrap_or_default(),
+            price: food.price.map_or(0.0, |cents| cents as f32 / 100.0),
+            nutrition: NutritionInput::new(food.nutrition.clone()),
+            food,
+            conflict: None,
+            confirm_discard: false,
+        }
+    }
+
+    /// Catch up with the [`Food`] as it is now in the [`Fridge`]. Without unsaved changes,
+    /// the fields are simply reloaded. Otherwise the changes are kept and a warning says
+    /// that saving will overwrite the external ones
+    fn reload(&mut self) {
+        let current = Fridge::open()
+            .into_iter()
+            .find(|food| food.id == self.food.id);
+        match current {
+            Some(food) if food == self.food => (),
+            Some(food) if !self.has_changes() => *self = Self::new(food),
+            Some(food) => {
+                self.conflict = Some(format!(
+                    "'{}' has been changed outside of the app, saving will overwrite those changes",
+                    food.name
+                ));
+                self.food = food;
+            }
+            None => {
+                self.conflict = Some(format!(
+                    "'{}' has been removed outside of the app, saving will add it back",
+                    self.food.name
+                ));
+            }
+        }
+    }
+
+    /// Returns whether a field differs from the [`Food`] as it was before editing
+    fn has_changes(&self) -> bool {
+        self.name != self.food.name
+            || self.date.best_before() != self.food.best_before
+            || self.category != self.food.category.clone().unwrap_or_default()
+            || self.location != self.food.location.clone().unwrap_or_default()
+            || self.store != self.food.store.clone().unwrap_or_default()
+            || to_cents(self.price) != self.food.price
+            || self.nutrition.value() != self.food.nutrition
+    }
+
+    /// Render the [`EditFoodWindow`]. Return false once it has been closed, either saving
+    /// or cancelling
+    pub fn show(&mut self, ctx: &egui::Context) -> bool {
+        let mut open = true;
+        let mut closed = ctx.input().key_pressed(egui::Key::Escape);
+        egui::Window::new("Edit food")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                if let Some(conflict) = &self.conflict {
+                    ui.colored_label(egui::Color32::YELLOW, conflict);
+                }
+
+                // Pressing Enter in any field saves the food
+                let mut submitted = false;
+                egui::Grid::new("edit food grid")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.add(AddFoodMenu::field_label("Name"));
+                        let field = ui.text_edit_singleline(&mut self.name);
+                        submitted |= submitted_with_enter(ui, &field);
+                        ui.end_row();
+
+                        ui.add(AddFoodMenu::field_label("Best before"));
+                        ui.horizontal(|ui| {
+                            self.date.calendar_ui(ui);
+                            let field = self.date.text_ui(ui);
+                            submitted |= submitted_with_enter(ui, &field);
+                        });
+                        ui.end_row();
+
+                        ui.add(AddFoodMenu::field_label("Category"));
+                        let field = ui.text_edit_singleline(&mut self.category);
+                        submitted |= submitted_with_enter(ui, &field);
+                        ui.end_row();
+
+                        ui.add(AddFoodMenu::field_label("Location"));
+                        let field = ui.text_edit_singleline(&mut self.location);
+                        submitted |= submitted_with_enter(ui, &field);
+                        ui.end_row();
+
+                        ui.add(AddFoodMenu::field_label("Store"));
+                        let field = ui.text_edit_singleline(&mut self.store);
+                        submitted |= submitted_with_enter(ui, &field);
+                        ui.end_row();
+
+                        ui.add(AddFoodMenu::field_label("Price"));
+                        ui.add(
+                            egui::widgets::DragValue::new(&mut self.price)
+                                .clamp_range(0.0..=10_000.0)
+                                .speed(0.05)
+                                .max_decimals(2)
+                                .suffix(format!(" {}", config::current().currency)),
+                        );
+                        ui.end_row();
+                    });
+                self.nutrition.ui(ui);
+
+                if self.confirm_discard {
+                    closed |= self.confirm_discard_ui(ui);
+                    return;
+                }
+                ui.horizontal(|ui| {
+                    let valid = !self.name.trim().is_empty() && self.date.is_valid();
+                    let save = ui.add_enabled(valid, egui::widgets::Button::new("Save"));
+                    if save.clicked() || (submitted && valid) {
+                        self.save();
+                        closed = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        closed = true;
+                    }
+                    if ui.button("Discard").clicked() {
+                        self.confirm_discard = true;
+                    }
+                });
+            });
+        open && !closed
+    }
+
+    /// Ask whether to throw the [`Food`] away, which counts as wasted money. Return true
+    /// once it has been thrown away
+    fn confirm_discard_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        ui.separator();
+        ui.label(format!(
+            "Throw '{}' away? It will count as wasted rather than eaten.",
+            self.food.name
+        ));
+        let mut discarded = false;
+        ui.horizontal(|ui| {
+            let discard = egui::widgets::Button::new(
+                egui::RichText::new("Discard").color(egui::Color32::WHITE),
+            )
+            .fill(egui::Color32::DARK_RED);
+            if ui.add(discard).clicked() {
+                if update_fridge(Fridge::open().discard(self.food.id)) {
+                    sound::play(SoundEvent::Discarded);
+                }
+                discarded = true;
+            }
+            if ui.button("Keep it").clicked() {
+                self.confirm_discard = false;
+            }
+        });
+        discarded
+    }
+
+    /// Replace the [`Food`] in the [`Fridge`] with the edited one
+    fn save(&self) {
+        let trimmed = |text: &str| Some(text.trim().to_string()).filter(|text| !text.is_empty());
+        let patch = FoodPatch {
+            name: Some(self.name.trim().to_string()),
+            best_before: Some(self.date.best_before()),
+            category: Some(trimmed(&self.category)),
+            location: Some(trimmed(&self.location)),
+            nutrition: Some(self.nutrition.value()),
+            price: Some(to_cents(self.price)),
+            store: Some(trimmed(&self.store)),
+            ..Default::default()
+        };
+
+        // Removed outside of the app while being edited: add it back as the user sees it
+        let fridge = Fridge::open();
+        if fridge.get(self.food.id).is_err() {
+            let mut food = self.food.clone();
+            patch.apply(&mut food);
+            fridge.add(food).update();
+            return;
+        }
+        update_fridge(fridge.patch(self.food.id, patch));
+    }
+}
+
+/// Translate the [`BestBefore`] into a [`egui::Color32`], with the colours of the config
+fn state_color(best_before: BestBefore) -> egui::Color32 {
+    let config = config::current();
+    let [r, g, b] = match best_before.state() {
+        FoodState::FarFromExpiring => config.appearance.far_from_expiring_color,
+        FoodState::CloseFromExpiring => config.appearance.close_from_expiring_color,
+        FoodState::Expired => config.appearance.expired_color,
+    };
+    egui::Color32::from_rgb(r, g, b)
+}
+
+/// When a [`Leftover`] was cooked, and what with
+fn describe_leftover(leftover: &Leftover, date_format: &str) -> String {
+    let mut text = format!(
+        "Cooked on {}",
+        BestBefore::from(leftover.cooked_on).format(date_format)
+    );
+    if !leftover.dish.is_empty() {
+        text = format!("{}, {}", leftover.dish, text.to_lowercase());
+    }
+    if !leftover.ingredients.is_empty() {
+        let names = leftover
+            .ingredients
+            .iter()
+            .map(|ingredient| ingredient.name.as_str())
+            .collect::<Vec<_>>();
+        text.push_str(&format!(", made with {}", names.join(", ")));
+    }
+    text
+}
+
+/// How long ago a [`Food`] was added, in the unit that reads best. Empty when unknown
+fn format_age(days: Option<u32>) -> String {
+    match days {
+        None => String::new(),
+        Some(0) => "today".to_string(),
+        Some(days @ 1..=13) => format!("{} d", days),
+        Some(days @ 14..=59) => format!("{} wk", days / 7),
+        Some(days @ 60..=729) => format!("{} mo", days / 30),
+        Some(days) => format!("{} yr", days / 365),
+    }
+}
+
+/// A price typed in the currency of the settings, in cents. [`None`] when 0, which means
+/// unknown
+#[inline]
+fn to_cents(price: f32) -> Option<u32> {
+    (price > 0.0).then(|| (price * 100.0).round() as u32)
+}
+
+/// Persist the [`Fridge`] after changing a [`Food`] by id. The [`Food`] may have been
+/// removed in the meantime, on another device for instance: there is nothing left to
+/// change then, so just log it. Return whether the [`Food`] was changed
+fn update_fridge(fridge: Result<Fridge, FridgeError>) -> bool {
+    match fridge {
+        Ok(mut fridge) => {
+            fridge.update();
+            true
+        }
+        Err(err) => {
+            log::warning(err);
+            false
         }
     }
 }
 
+/// Returns whether the user left the text field by pressing Enter, which submits the form
+/// the field belongs to
+#[inline]
+fn submitted_with_enter(ui: &egui::Ui, field: &egui::Response) -> bool {
+    field.lost_focus() && ui.input().key_pressed(egui::Key::Enter)
+}
+
 /// Add custom fonts to the UI
 #[inline]
 fn setup_custom_fonts(ctx: &egui::Context) {
@@ -0,0 +1,119 @@
+//! The Expiry alerts window, see [`AlertsWindow`]
+
+use eframe::egui;
+use std::mem;
+
+use super::state_color;
+use crate::app::alerts::{AlertLevel, Alerts};
+use crate::app::backend::{self, Food, Fridge};
+use crate::app::config;
+
+/// The [`AlertsWindow`] summarizes the expired and expiring foods not dismissed yet. Each
+/// one can be dismissed, or all the alerts snoozed
+pub struct AlertsWindow {
+    /// Read when the window opens, and again when the files change or the day rolls over
+    alerts: Alerts,
+
+    /// The foods to alert about, from the [`Alerts`] and the inventory in use
+    pending: Vec<(Food, AlertLevel)>,
+}
+
+impl AlertsWindow {
+    pub fn new() -> Self {
+        let alerts = Alerts::open();
+        Self {
+            pending: alerts.pending(Fridge::open()),
+            alerts,
+        }
+    }
+
+    /// Read the alerts and the inventory again, after they changed
+    #[inline]
+    pub fn reload(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Returns whether there is something to alert about, and the alerts are not snoozed
+    #[inline]
+    pub fn is_due(&self) -> bool {
+        !self.pending.is_empty() && !self.alerts.is_snoozed()
+    }
+
+    /// Show the window. Return false once it is closed, snoozed, or left with nothing to
+    /// alert about
+    pub fn show(&mut self, ctx: &egui::Context) -> bool {
+        let config = config::current();
+        let pending = &self.pending;
+        if pending.is_empty() {
+            return false;
+        }
+
+        let mut dismissed = Vec::new();
+        let mut snoozed = false;
+        let mut open = !ctx.input().key_pressed(egui::Key::Escape);
+        egui::Window::new("Expiry alerts")
+            .open(&mut open)
+            .collapsible(false)
+            .vscroll(true)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                let expired = pending
+                    .iter()
+                    .filter(|(_, level)| *level == AlertLevel::Expired)
+                    .count();
+                ui.label(format!(
+                    "{} expired, {} expiring soon",
+                    expired,
+                    pending.len() - expired
+                ));
+                egui::Grid::new("alerts grid")
+                    .num_columns(3)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for (food, level) in pending {
+                            ui.label(&food.name);
+                            ui.label(
+                                egui::RichText::new(food.best_before.format(&config.date_format))
+                                    .color(state_color(food.best_before)),
+                            );
+                            if ui.button("Dismiss").clicked() {
+                                dismissed.push((food.id, *level));
+                            }
+                            ui.end_row();
+                        }
+                    });
+
+                ui.horizontal(|ui| {
+                    let snooze = format!("Snooze {} min", config.alerts.snooze_minutes);
+                    snoozed = ui.button(snooze).clicked();
+                    if ui.button("Dismiss all").clicked() {
+                        dismissed.extend(pending.iter().map(|(food, level)| (food.id, *level)));
+                    }
+                });
+            });
+
+        if snoozed {
+            mem::take(&mut self.alerts)
+                .snooze(config.alerts.snooze_minutes)
+                .update();
+            return false;
+        } else if !dismissed.is_empty() {
+            let foods = backend::all_foods()
+                .into_iter()
+                .map(|(_, food)| food)
+                .collect::<Vec<_>>();
+            self.alerts = dismissed
+                .into_iter()
+                .fold(mem::take(&mut self.alerts), |alerts, (id, level)| {
+                    alerts.dismiss(id, level)
+                })
+                .forget_missing(&foods);
+            self.alerts.update();
+            let shown = mem::take(&mut self.pending)
+                .into_iter()
+                .map(|(food, _)| food);
+            self.pending = self.alerts.pending(shown);
+        }
+        open
+    }
+}
@@ -0,0 +1,151 @@
+//! The Intake window, see [`IntakeWindow`]
+
+use chrono::TimeZone;
+use eframe::egui;
+use std::mem;
+
+use crate::app::backend::BestBefore;
+use crate::app::clock;
+use crate::app::config;
+use crate::app::nutrition::{IntakeLog, Nutrients};
+
+/// The [`IntakeWindow`] sums up the nutrients of the foods eaten on a day, and lists the
+/// previous days
+pub struct IntakeWindow {
+    /// The day shown
+    day: chrono::NaiveDate,
+
+    /// Read when the window opens, and again when the file changes
+    log: IntakeLog,
+}
+
+impl IntakeWindow {
+    pub fn new() -> Self {
+        Self {
+            day: clock::today(),
+            log: IntakeLog::open(),
+        }
+    }
+
+    /// Read the intake log again, after it changed
+    #[inline]
+    pub fn reload(&mut self) {
+        self.log = IntakeLog::open();
+    }
+
+    /// The day changed: showing the former today moves to the new one
+    #[inline]
+    pub fn roll_over(&mut self, yesterday: chrono::NaiveDate) {
+        if self.day == yesterday {
+            self.day = clock::today();
+        }
+    }
+
+    /// Show the window. Return false once it is closed
+    pub fn show(&mut self, ctx: &egui::Context) -> bool {
+        let config = config::current();
+        let log = &self.log;
+        let today = clock::today();
+        let mut removed = None;
+        let mut open = !ctx.input().key_pressed(egui::Key::Escape);
+        egui::Window::new("Intake")
+            .open(&mut open)
+            .collapsible(false)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.small_button("<").clicked() {
+                        self.day = self.day.pred_opt().unwrap_or(self.day);
+                    }
+                    ui.heading(BestBefore::from(self.day).format(&config.date_format));
+                    if ui
+                        .add_enabled(self.day < today, egui::widgets::Button::new(">").small())
+                        .clicked()
+                    {
+                        self.day = self.day.succ_opt().unwrap_or(self.day);
+                    }
+                    if self.day != today && ui.small_button("Today").clicked() {
+                        self.day = today;
+                    }
+                });
+
+                let intakes = log.day(self.day);
+                if intakes.is_empty() {
+                    ui.label("Nothing eaten");
+                } else {
+                    ui.label(egui::RichText::new(describe_nutrients(log.total(self.day))).strong());
+                    egui::Grid::new("intake grid")
+                        .num_columns(4)
+                        .striped(true)
+                        .show(ui, |ui| {
+                            for (index, intake) in intakes.iter().enumerate() {
+                                let time = chrono::Local
+                                    .timestamp_millis_opt(intake.timestamp)
+                                    .single()
+                                    .map(|time| time.format("%H:%M").to_string())
+                                    .unwrap_or_default();
+                                ui.label(time);
+                                ui.label(&intake.name);
+                                match intake.nutrients {
+                                    Some(nutrients) => {
+                                        ui.label(format!("{:.0} kcal", nutrients.kcal))
+                                            .on_hover_text(describe_nutrients(nutrients));
+                                    }
+                                    None => {
+                                        ui.weak("No nutrition facts");
+                                    }
+                                }
+                                if ui
+                                    .small_button("Remove")
+                                    .on_hover_text("It was not eaten after all")
+                                    .clicked()
+                                {
+                                    removed = Some(index);
+                                }
+                                ui.end_row();
+                            }
+                        });
+                }
+
+                ui.separator();
+                ui.heading("History");
+                egui::Grid::new("intake history grid")
+                    .num_columns(6)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for header in ["Day", "Foods", "Energy", "Protein", "Carbs", "Fat"] {
+                            ui.label(egui::RichText::new(header).strong());
+                        }
+                        ui.end_row();
+
+                        for day in log.days() {
+                            let total = log.total(day);
+                            let text = BestBefore::from(day).format(&config.date_format);
+                            if ui.selectable_label(day == self.day, text).clicked() {
+                                self.day = day;
+                            }
+                            ui.label(log.day(day).len().to_string());
+                            ui.label(format!("{:.0} kcal", total.kcal));
+                            ui.label(format!("{:.1} g", total.protein));
+                            ui.label(format!("{:.1} g", total.carbs));
+                            ui.label(format!("{:.1} g", total.fat));
+                            ui.end_row();
+                        }
+                    });
+            });
+
+        if let Some(index) = removed {
+            self.log = mem::take(&mut self.log).remove(self.day, index);
+            self.log.update();
+        }
+        open
+    }
+}
+
+/// Energy and macronutrients in one line
+fn describe_nutrients(nutrients: Nutrients) -> String {
+    format!(
+        "{:.0} kcal, {:.1} g protein, {:.1} g carbs, {:.1} g fat",
+        nutrients.kcal, nutrients.protein, nutrients.carbs, nutrients.fat
+    )
+}
@@ -0,0 +1,688 @@
+//! The Settings window, see [`SettingsWindow`]
+
+use eframe::egui;
+
+#[cfg(feature = "server")]
+use crate::app::config::WebhookEvent;
+use crate::app::config::{self, Config, LogRotation};
+#[cfg(all(feature = "cli", feature = "server"))]
+use crate::app::config::{DigestFrequency, SmtpSecurity};
+use crate::app::log;
+
+/// The [`SettingsWindow`] edits the [`Config`]. Changes are applied live, saving persists
+/// them in the config file and cancelling restores the settings as they were.
+pub struct SettingsWindow {
+    /// The settings being edited
+    draft: Config,
+
+    /// The settings as they were when the window was opened
+    original: Config,
+
+    /// Name of the inventory about to be added
+    new_inventory: String,
+
+    /// Type of dish about to be given a shelf life
+    new_dish: String,
+}
+
+impl Default for SettingsWindow {
+    fn default() -> Self {
+        let config = Config::clone(&config::current());
+        Self {
+            draft: config.clone(),
+            original: config,
+            new_inventory: String::new(),
+            new_dish: String::new(),
+        }
+    }
+}
+
+impl SettingsWindow {
+    /// Render the [`SettingsWindow`]. Return false once it has been closed, either saving
+    /// or cancelling
+    pub fn show(&mut self, ctx: &egui::Context) -> bool {
+        let mut open = true;
+        let mut saved = false;
+        let mut cancelled = ctx.input().key_pressed(egui::Key::Escape);
+        egui::Window::new("Settings")
+            .open(&mut open)
+            .collapsible(false)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                self.appearance_ui(ui);
+                self.behaviour_ui(ui);
+                self.sounds_ui(ui);
+                self.leftovers_ui(ui);
+                self.inventories_ui(ui);
+                self.paths_ui(ui);
+                self.sync_ui(ui);
+                self.logging_ui(ui);
+                #[cfg(all(feature = "cli", feature = "server"))]
+                self.digest_ui(ui);
+                #[cfg(feature = "server")]
+                self.webhooks_ui(ui);
+                #[cfg(all(feature = "cli", feature = "server"))]
+                self.mqtt_ui(ui);
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    let valid = config::is_valid_date_format(&self.draft.date_format);
+                    if ui
+                        .add_enabled(valid, egui::widgets::Button::new("Save"))
+                        .clicked()
+                    {
+                        saved = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        cancelled = true;
+                    }
+                    if ui.button("Defaults").clicked() {
+                        self.reset_draft();
+                    }
+                });
+            });
+
+        if saved {
+            config::save(self.draft.clone());
+            return false;
+        }
+        if cancelled || !open {
+            config::apply(self.original.clone());
+            return false;
+        }
+
+        // Apply the changes live, unless they would break the date display
+        if config::is_valid_date_format(&self.draft.date_format) && self.draft != *config::current()
+        {
+            config::apply(self.draft.clone());
+        }
+        true
+    }
+
+    /// Font sizes, table sizes and colours
+    fn appearance_ui(&mut self, ui: &mut egui::Ui) {
+        let appearance = &mut self.draft.appearance;
+        ui.heading("Appearance");
+        egui::Grid::new("appearance settings grid")
+            .num_columns(2)
+            .show(ui, |ui| {
+                for (text, value, range) in [
+                    ("Font size", &mut appearance.font_size, 8.0..=64.0),
+                    (
+                        "Header font size",
+                        &mut appearance.header_font_size,
+                        8.0..=64.0,
+                    ),
+                    ("Menu font size", &mut appearance.menu_font_size, 8.0..=64.0),
+                    ("Row height", &mut appearance.row_height, 10.0..=100.0),
+                    (
+                        "Best before column width",
+                        &mut appearance.best_before_column_width,
+                        50.0..=500.0,
+                    ),
+                    (
+                        "Age column width",
+                        &mut appearance.age_column_width,
+                        50.0..=500.0,
+                    ),
+                    (
+                        "Button column width",
+                        &mut appearance.button_column_width,
+                        50.0..=500.0,
+                    ),
+                ] {
+                    ui.label(text);
+                    ui.add(egui::widgets::DragValue::new(value).clamp_range(range));
+                    ui.end_row();
+                }
+
+                for (text, color) in [
+                    ("Far from expiring", &mut appearance.far_from_expiring_color),
+                    (
+                        "Close from expiring",
+                        &mut appearance.close_from_expiring_color,
+                    ),
+                    ("Expired", &mut appearance.expired_color),
+                ] {
+                    ui.label(text);
+                    ui.color_edit_button_srgb(color);
+                    ui.end_row();
+                }
+            });
+    }
+
+    /// Expiry thresholds, alerts, notifications, quantity, date format and window size
+    fn behaviour_ui(&mut self, ui: &mut egui::Ui) {
+        let draft = &mut self.draft;
+        ui.heading("Behaviour");
+        egui::Grid::new("behaviour settings grid")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Expired with days left");
+                ui.add(egui::widgets::DragValue::new(
+                    &mut draft.expiry.expired_days,
+                ));
+                ui.end_row();
+
+                ui.label("Close from expiring with days left");
+                ui.add(
+                    egui::widgets::DragValue::new(&mut draft.expiry.close_days)
+                        .clamp_range(draft.expiry.expired_days..=u16::MAX),
+                );
+                ui.end_row();
+
+                ui.label("Expiry alerts");
+                ui.checkbox(&mut draft.alerts.enabled, "Alert about expiring foods");
+                ui.end_row();
+
+                ui.label("Check for alerts every (min)");
+                ui.add(
+                    egui::widgets::DragValue::new(&mut draft.alerts.interval_minutes)
+                        .clamp_range(1..=24 * 60),
+                );
+                ui.end_row();
+
+                ui.label("Snooze alerts for (min)");
+                ui.add(
+                    egui::widgets::DragValue::new(&mut draft.alerts.snooze_minutes)
+                        .clamp_range(1..=7 * 24 * 60),
+                );
+                ui.end_row();
+
+                ui.label("Desktop notifications");
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut draft.notifications.enabled, "Enabled");
+                    ui.add_enabled_ui(draft.notifications.enabled, |ui| {
+                        ui.checkbox(&mut draft.notifications.expiring, "Expiring");
+                        ui.checkbox(&mut draft.notifications.expired, "Expired");
+                    });
+                });
+                ui.end_row();
+
+                ui.label("Daemon checks every (min)");
+                ui.add(
+                    egui::widgets::DragValue::new(&mut draft.notifications.interval_minutes)
+                        .clamp_range(1..=24 * 60),
+                );
+                ui.end_row();
+
+                ui.label("Max quantity");
+                ui.add(
+                    egui::widgets::DragValue::new(&mut draft.add_food.max_quantity)
+                        .clamp_range(1_u8..=u8::MAX),
+                );
+                ui.end_row();
+
+                ui.label("Long stored after (days)");
+                ui.add(
+                    egui::widgets::DragValue::new(&mut draft.ageing.long_stored_days)
+                        .clamp_range(1..=3650),
+                );
+                ui.end_row();
+
+                ui.label("Currency");
+                ui.text_edit_singleline(&mut draft.currency);
+                ui.end_row();
+
+                ui.label("Date format");
+                ui.horizontal(|ui| {
+                    ui.text_edit_singleline(&mut draft.date_format);
+                    if !config::is_valid_date_format(&draft.date_format) {
+                        ui.label(
+                            egui::RichText::new("Not a valid format").color(egui::Color32::RED),
+                        );
+                    }
+                });
+                ui.end_row();
+
+                ui.label("Window size");
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::widgets::DragValue::new(&mut draft.window.width)
+                            .clamp_range(200.0..=4000.0),
+                    );
+                    ui.label("x");
+                    ui.add(
+                        egui::widgets::DragValue::new(&mut draft.window.height)
+                            .clamp_range(200.0..=4000.0),
+                    );
+                });
+                ui.end_row();
+            });
+    }
+
+    /// Volume, mute and the sound of each event. An empty path plays no sound
+    fn sounds_ui(&mut self, ui: &mut egui::Ui) {
+        let sound = &mut self.draft.sound;
+        ui.heading("Sounds");
+        if !cfg!(feature = "audio") {
+            ui.label("This build of Food Fortress has no audio, the sounds are never played");
+        }
+        egui::Grid::new("sounds settings grid")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Sounds");
+                ui.checkbox(&mut sound.enabled, "Play sounds (M to mute)");
+                ui.end_row();
+
+                ui.label("Volume");
+                ui.add(egui::widgets::Slider::new(&mut sound.volume, 0.0..=1.0));
+                ui.end_row();
+
+                for (text, path) in [
+                    ("Food added", &mut sound.added),
+                    ("Food opened", &mut sound.opened),
+                    ("Food eaten", &mut sound.eaten),
+                    ("Food discarded", &mut sound.discarded),
+                    ("Foods expiring", &mut sound.expiry_alert),
+                ] {
+                    ui.label(text);
+                    ui.text_edit_singleline(path);
+                    ui.end_row();
+                }
+            });
+    }
+
+    /// How many days the leftovers of each type of dish keep
+    fn leftovers_ui(&mut self, ui: &mut egui::Ui) {
+        let leftovers = &mut self.draft.leftovers;
+        ui.heading("Leftovers");
+        let mut removed = None;
+        egui::Grid::new("leftovers settings grid")
+            .num_columns(3)
+            .show(ui, |ui| {
+                for (dish, days) in leftovers.dishes.iter_mut() {
+                    ui.label(dish.as_str());
+                    ui.add(
+                        egui::widgets::DragValue::new(days)
+                            .clamp_range(0..=365)
+                            .suffix(" days"),
+                    );
+                    if ui.button("Remove").clicked() {
+                        removed = Some(dish.clone());
+                    }
+                    ui.end_row();
+                }
+
+                ui.label("Other dishes");
+                ui.add(
+                    egui::widgets::DragValue::new(&mut leftovers.default_days)
+                        .clamp_range(0..=365)
+                        .suffix(" days"),
+                );
+                ui.end_row();
+
+                ui.text_edit_singleline(&mut self.new_dish);
+                let dish = self.new_dish.trim();
+                let valid = !dish.is_empty()
+                    && !leftovers
+                        .dishes
+                        .keys()
+                        .any(|known| known.to_lowercase() == dish.to_lowercase());
+                if ui
+                    .add_enabled(valid, egui::widgets::Button::new("Add"))
+                    .clicked()
+                {
+                    leftovers
+                        .dishes
+                        .insert(dish.to_string(), leftovers.default_days);
+                    self.new_dish.clear();
+                }
+                ui.end_row();
+            });
+        if let Some(dish) = removed {
+            leftovers.dishes.remove(&dish);
+        }
+    }
+
+    /// Inventories and the paths to their json files. The inventory in use cannot be removed
+    fn inventories_ui(&mut self, ui: &mut egui::Ui) {
+        let draft = &mut self.draft;
+        ui.heading("Inventories");
+        let mut removed = None;
+        egui::Grid::new("inventories settings grid")
+            .num_columns(3)
+            .show(ui, |ui| {
+                for (name, path) in draft.inventories.iter_mut() {
+                    ui.label(name.as_str());
+                    ui.text_edit_singleline(path);
+                    let removable = *name != draft.inventory;
+                    if ui
+                        .add_enabled(removable, egui::widgets::Button::new("Remove"))
+                        .clicked()
+                    {
+                        removed = Some(name.clone());
+                    }
+                    ui.end_row();
+                }
+
+                ui.text_edit_singleline(&mut self.new_inventory);
+                let name = self.new_inventory.trim();
+                let valid = !name.is_empty() && !draft.inventories.contains_key(name);
+                if ui
+                    .add_enabled(valid, egui::widgets::Button::new("Add"))
+                    .clicked()
+                {
+                    draft
+                        .inventories
+                        .insert(name.to_string(), config::new_inventory_path(name));
+                    self.new_inventory.clear();
+                }
+                ui.end_row();
+            });
+        if let Some(name) = removed {
+            draft.inventories.remove(&name);
+        }
+    }
+
+    /// Reset the draft to the default settings, except for what identifies the data of
+    /// this device: the inventories, the one in use and the name in the synced folder
+    fn reset_draft(&mut self) {
+        let defaults = Config::default();
+        self.draft = Config {
+            inventories: self.draft.inventories.clone(),
+            inventory: self.draft.inventory.clone(),
+            sync: config::Sync {
+                device: self.draft.sync.device.clone(),
+                ..defaults.sync
+            },
+            ..defaults
+        };
+    }
+
+    /// Paths to the data files
+    fn paths_ui(&mut self, ui: &mut egui::Ui) {
+        let paths = &mut self.draft.paths;
+        ui.heading("Data files");
+        egui::Grid::new("paths settings grid")
+            .num_columns(2)
+            .show(ui, |ui| {
+                for (text, path) in [
+                    ("Products", &mut paths.products),
+                    ("Known foods", &mut paths.known_foods),
+                    ("Alerts", &mut paths.alerts),
+                    ("Notifications", &mut paths.notifications),
+                    ("Digest", &mut paths.digest),
+                    ("Webhooks", &mut paths.webhooks),
+                    ("Intake", &mut paths.intake),
+                    ("Waste", &mut paths.waste),
+                ] {
+                    ui.label(text);
+                    ui.text_edit_singleline(path);
+                    ui.end_row();
+                }
+            });
+    }
+
+    /// Sharing the inventories with other devices through a synced folder
+    fn sync_ui(&mut self, ui: &mut egui::Ui) {
+        let sync = &mut self.draft.sync;
+        ui.heading("Sync");
+        ui.checkbox(
+            &mut sync.enabled,
+            "Sync the inventories through a shared folder",
+        );
+        egui::Grid::new("sync settings grid")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Folder");
+                ui.text_edit_singleline(&mut sync.folder);
+                ui.end_row();
+
+                ui.label("Device");
+                ui.text_edit_singleline(&mut sync.device);
+                ui.end_row();
+
+                ui.label("Compact after (days)");
+                ui.add(
+                    egui::widgets::DragValue::new(&mut sync.compact_after_days)
+                        .clamp_range(1..=365),
+                );
+                ui.end_row();
+            });
+    }
+
+    /// What is logged, and where
+    fn logging_ui(&mut self, ui: &mut egui::Ui) {
+        let logging = &mut self.draft.logging;
+        ui.heading("Log");
+        egui::Grid::new("logging settings grid")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Minimum level");
+                ui.horizontal(|ui| {
+                    for level in log::Level::ALL {
+                        ui.radio_value(&mut logging.level, level, level.to_string());
+                    }
+                });
+                ui.end_row();
+
+                ui.label("File");
+                ui.text_edit_singleline(&mut logging.path);
+                ui.end_row();
+
+                ui.label("Format");
+                ui.checkbox(&mut logging.json, "One json object per line");
+                ui.end_row();
+
+                ui.label("Rotation");
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut logging.rotation, LogRotation::Size, "By size");
+                    ui.radio_value(&mut logging.rotation, LogRotation::Daily, "Daily");
+                    ui.radio_value(&mut logging.rotation, LogRotation::Never, "Never");
+                });
+                ui.end_row();
+
+                if logging.rotation == LogRotation::Size {
+                    ui.label("Maximum size (KB)");
+                    ui.add(
+                        egui::widgets::DragValue::new(&mut logging.max_size_kb)
+                            .clamp_range(1..=1024 * 1024),
+                    );
+                    ui.end_row();
+                }
+
+                ui.label("Old log files kept");
+                ui.add(egui::widgets::DragValue::new(&mut logging.retention).clamp_range(0..=100));
+                ui.end_row();
+            });
+    }
+
+    /// Email digest to the household, and the SMTP server it is sent through
+    #[cfg(all(feature = "cli", feature = "server"))]
+    fn digest_ui(&mut self, ui: &mut egui::Ui) {
+        let digest = &mut self.draft.digest;
+        ui.heading("Email digest");
+        ui.checkbox(
+            &mut digest.enabled,
+            "Email the expired and expiring foods to the household",
+        );
+        egui::Grid::new("digest settings grid")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Frequency");
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut digest.frequency, DigestFrequency::Daily, "Daily");
+                    ui.radio_value(&mut digest.frequency, DigestFrequency::Weekly, "Weekly");
+                    if digest.frequency == DigestFrequency::Weekly {
+                        egui::ComboBox::from_id_source("digest weekday")
+                            .selected_text(digest.weekday.to_string())
+                            .show_ui(ui, |ui| {
+                                let mut weekday = chrono::Weekday::Mon;
+                                for _ in 0..7 {
+                                    ui.selectable_value(
+                                        &mut digest.weekday,
+                                        weekday,
+                                        weekday.to_string(),
+                                    );
+                                    weekday = weekday.succ();
+                                }
+                            });
+                    }
+                });
+                ui.end_row();
+
+                ui.label("Hour");
+                ui.add(egui::widgets::DragValue::new(&mut digest.hour).clamp_range(0..=23));
+                ui.end_row();
+
+                ui.label("From");
+                ui.text_edit_singleline(&mut digest.from);
+                ui.end_row();
+
+                ui.label("Recipients");
+                ui.vertical(|ui| {
+                    let mut removed = None;
+                    for (i, recipient) in digest.recipients.iter_mut().enumerate() {
+                        ui.horizontal(|ui| {
+                            ui.text_edit_singleline(recipient);
+                            if ui.small_button("🗑").clicked() {
+                                removed = Some(i);
+                            }
+                        });
+                    }
+                    if let Some(i) = removed {
+                        digest.recipients.remove(i);
+                    }
+                    if ui.button("Add recipient").clicked() {
+                        digest.recipients.push(String::new());
+                    }
+                });
+                ui.end_row();
+
+                let smtp = &mut digest.smtp;
+                ui.label("SMTP server");
+                ui.horizontal(|ui| {
+                    ui.text_edit_singleline(&mut smtp.host);
+                    ui.label("Port");
+                    ui.add(egui::widgets::DragValue::new(&mut smtp.port));
+                });
+                ui.end_row();
+
+                ui.label("Security");
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut smtp.security, SmtpSecurity::StartTls, "STARTTLS");
+                    ui.radio_value(&mut smtp.security, SmtpSecurity::Tls, "TLS");
+                    ui.radio_value(&mut smtp.security, SmtpSecurity::None, "None");
+                });
+                ui.end_row();
+
+                ui.label("Username");
+                ui.text_edit_singleline(&mut smtp.username);
+                ui.end_row();
+
+                ui.label("Password");
+                ui.add(egui::TextEdit::singleline(&mut smtp.password).password(true));
+                ui.end_row();
+            });
+    }
+
+    /// URLs POSTed to on every change of the inventories, for home automation
+    #[cfg(feature = "server")]
+    fn webhooks_ui(&mut self, ui: &mut egui::Ui) {
+        let webhooks = &mut self.draft.webhooks;
+        ui.heading("Webhooks");
+        ui.checkbox(
+            &mut webhooks.enabled,
+            "POST the changes of the inventories to these URLs",
+        );
+        egui::Grid::new("webhooks settings grid")
+            .num_columns(2)
+            .show(ui, |ui| {
+                let mut removed = None;
+                for (i, hook) in webhooks.hooks.iter_mut().enumerate() {
+                    ui.label("URL");
+                    ui.horizontal(|ui| {
+                        ui.text_edit_singleline(&mut hook.url);
+                        if ui.small_button("🗑").clicked() {
+                            removed = Some(i);
+                        }
+                    });
+                    ui.end_row();
+
+                    ui.label("Secret");
+                    ui.add(egui::TextEdit::singleline(&mut hook.secret).password(true));
+                    ui.end_row();
+
+                    ui.label("Events");
+                    ui.horizontal_wrapped(|ui| {
+                        for event in WebhookEvent::ALL {
+                            let mut wanted = hook.events.contains(&event);
+                            let text = format!("{:?}", event);
+                            if ui.checkbox(&mut wanted, text).changed() {
+                                hook.events.retain(|e| *e != event);
+                                if wanted {
+                                    hook.events.push(event);
+                                }
+                            }
+                        }
+                    });
+                    ui.end_row();
+                }
+                if let Some(i) = removed {
+                    webhooks.hooks.remove(i);
+                }
+
+                ui.label("");
+                if ui.button("Add webhook").clicked() {
+                    webhooks.hooks.push(config::Webhook::default());
+                }
+                ui.end_row();
+
+                ui.label("Attempts");
+                ui.add(
+                    egui::widgets::DragValue::new(&mut webhooks.max_attempts).clamp_range(1..=100),
+                );
+                ui.end_row();
+
+                ui.label("First retry after (s)");
+                ui.add(
+                    egui::widgets::DragValue::new(&mut webhooks.retry_after_seconds)
+                        .clamp_range(1..=24 * 60 * 60),
+                );
+                ui.end_row();
+            });
+    }
+
+    /// MQTT broker the daemon publishes the inventories to, for Home Assistant
+    #[cfg(all(feature = "cli", feature = "server"))]
+    fn mqtt_ui(&mut self, ui: &mut egui::Ui) {
+        let mqtt = &mut self.draft.mqtt;
+        ui.heading("MQTT");
+        ui.checkbox(
+            &mut mqtt.enabled,
+            "Publish the inventories to Home Assistant, from the daemon",
+        );
+        egui::Grid::new("mqtt settings grid")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Broker");
+                ui.horizontal(|ui| {
+                    ui.text_edit_singleline(&mut mqtt.host);
+                    ui.label("Port");
+                    ui.add(egui::widgets::DragValue::new(&mut mqtt.port));
+                });
+                ui.end_row();
+
+                ui.label("Username");
+                ui.text_edit_singleline(&mut mqtt.username);
+                ui.end_row();
+
+                ui.label("Password");
+                ui.add(egui::TextEdit::singleline(&mut mqtt.password).password(true));
+                ui.end_row();
+
+                ui.label("Topic prefix");
+                ui.text_edit_singleline(&mut mqtt.topic_prefix);
+                ui.end_row();
+
+                ui.label("Discovery prefix");
+                ui.text_edit_singleline(&mut mqtt.discovery_prefix);
+                ui.end_row();
+
+                ui.label("");
+                ui.checkbox(&mut mqtt.publish_foods, "A sensor per food");
+                ui.end_row();
+            });
+    }
+}
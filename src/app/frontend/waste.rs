@@ -0,0 +1,140 @@
+//! The Money wasted window, see [`WasteWindow`]
+
+use eframe::egui;
+
+use crate::app::clock;
+use crate::app::waste::{self, WasteLog};
+
+/// The [`WasteWindow`] reports the money wasted on the foods thrown away: how much
+/// less than last month so far, and month by month, per category
+pub struct WasteWindow {
+    /// First day of the month whose categories are shown
+    month: chrono::NaiveDate,
+
+    /// Read when the window opens, and again when the file changes
+    log: WasteLog,
+}
+
+impl WasteWindow {
+    pub fn new() -> Self {
+        Self {
+            month: waste::first_of_month(clock::today()),
+            log: WasteLog::open(),
+        }
+    }
+
+    /// Read the waste log again, after it changed
+    #[inline]
+    pub fn reload(&mut self) {
+        self.log = WasteLog::open();
+    }
+
+    /// Show the window. Return false once it is closed
+    pub fn show(&mut self, ctx: &egui::Context) -> bool {
+        let log = &self.log;
+        let today = clock::today();
+        let mut open = !ctx.input().key_pressed(egui::Key::Escape);
+        egui::Window::new("Money wasted")
+            .open(&mut open)
+            .collapsible(false)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                let this_month = log.report(today);
+                ui.heading(format!(
+                    "{} wasted this month, {} food{} thrown away",
+                    waste::format_money(this_month.wasted as i64),
+                    this_month.foods,
+                    if this_month.foods == 1 { "" } else { "s" }
+                ));
+                let saved = log.saved_vs_last_month(today);
+                let (text, color) = if saved >= 0 {
+                    ("saved", egui::Color32::GREEN)
+                } else {
+                    ("more wasted", egui::Color32::RED)
+                };
+                ui.label(
+                    egui::RichText::new(format!(
+                        "{} {} than by this day last month",
+                        waste::format_money(saved.abs()),
+                        text
+                    ))
+                    .strong()
+                    .color(color),
+                );
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.small_button("<").clicked() {
+                        self.month =
+                            waste::first_of_month(self.month.pred_opt().unwrap_or(self.month));
+                    }
+                    ui.heading(self.month.format("%B %Y").to_string());
+                    let next_month = self.month + chrono::Duration::days(31);
+                    if ui
+                        .add_enabled(next_month <= today, egui::widgets::Button::new(">").small())
+                        .clicked()
+                    {
+                        self.month = waste::first_of_month(next_month);
+                    }
+                });
+                let report = log.report(self.month);
+                if report.foods == 0 {
+                    ui.label("Nothing thrown away");
+                } else {
+                    Self::categories_ui(ui, &report);
+                }
+
+                ui.separator();
+                ui.heading("History");
+                egui::Grid::new("waste history grid")
+                    .num_columns(3)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for header in ["Month", "Foods", "Wasted"] {
+                            ui.label(egui::RichText::new(header).strong());
+                        }
+                        ui.end_row();
+
+                        for month in log.months() {
+                            let report = log.report(month);
+                            let text = month.format("%B %Y").to_string();
+                            if ui.selectable_label(month == self.month, text).clicked() {
+                                self.month = month;
+                            }
+                            ui.label(report.foods.to_string());
+                            ui.label(waste::format_money(report.wasted as i64));
+                            ui.end_row();
+                        }
+                    });
+            });
+        open
+    }
+
+    /// The money wasted in a month per category, the most expensive first
+    fn categories_ui(ui: &mut egui::Ui, report: &waste::MonthReport) {
+        let mut categories = report.by_category.iter().collect::<Vec<_>>();
+        categories.sort_by(|(_, (_, a)), (_, (_, b))| b.cmp(a));
+        egui::Grid::new("waste categories grid")
+            .num_columns(3)
+            .striped(true)
+            .show(ui, |ui| {
+                for header in ["Category", "Foods", "Wasted"] {
+                    ui.label(egui::RichText::new(header).strong());
+                }
+                ui.end_row();
+
+                for (category, (foods, wasted)) in categories {
+                    ui.label(category);
+                    ui.label(foods.to_string());
+                    ui.label(waste::format_money(*wasted as i64));
+                    ui.end_row();
+                }
+            });
+        if report.unpriced > 0 {
+            ui.weak(format!(
+                "{} of the {} foods had no price",
+                report.unpriced, report.foods
+            ));
+        }
+    }
+}
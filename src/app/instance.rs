@@ -0,0 +1,38 @@
+//! The instance mod makes sure a single GUI runs at a time, so that two windows don't
+//! overwrite each other's changes to the data files.
+
+use fs2::FileExt;
+use std::fs;
+use std::io;
+
+/// Path to the lock file
+const LOCK: &str = "food-fortress.lock";
+
+/// The [`InstanceLock`] is held as long as the GUI runs. The OS releases it when the
+/// process ends, even if it crashes, so a stale lock file never prevents the app from
+/// starting
+pub struct InstanceLock {
+    _file: fs::File,
+}
+
+impl InstanceLock {
+    /// Take the lock. Fails with [`io::ErrorKind::WouldBlock`] when another instance holds it
+    pub fn acquire() -> io::Result<Self> {
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(false)
+            .open(LOCK)?;
+        file.try_lock_exclusive().map_err(|err| {
+            if err.raw_os_error() == fs2::lock_contended_error().raw_os_error() {
+                io::Error::new(
+                    io::ErrorKind::WouldBlock,
+                    "Food Fortress is already running",
+                )
+            } else {
+                err
+            }
+        })?;
+        Ok(Self { _file: file })
+    }
+}
@@ -1,3 +1,24 @@
-mod backend;
+#[cfg(any(feature = "gui", feature = "cli"))]
+use food_fortress_core::{alerts, backend};
+pub use food_fortress_core::{clock, config, log, products};
+#[cfg(feature = "gui")]
+use food_fortress_core::{known_foods, nutrition, sync, waste};
+
+#[cfg(feature = "gui")]
+pub mod diagnostics;
+#[cfg(all(feature = "cli", feature = "server"))]
+pub mod digest;
+#[cfg(feature = "gui")]
 pub mod frontend;
-pub mod log;
+#[cfg(feature = "gui")]
+pub mod instance;
+#[cfg(all(feature = "cli", feature = "server"))]
+pub mod mqtt;
+#[cfg(any(feature = "gui", feature = "cli"))]
+pub mod notifications;
+#[cfg(feature = "gui")]
+mod sound;
+#[cfg(feature = "gui")]
+mod watcher;
+#[cfg(all(feature = "server", any(feature = "gui", feature = "cli")))]
+pub mod webhooks;
@@ -0,0 +1,334 @@
+//! The mqtt mod connects `food-fortress daemon` to an MQTT broker, for Home Assistant. It
+//! publishes how many foods are expired, close from expiring and far from expiring, and a
+//! sensor per food with its days left, all retained and announced through the Home
+//! Assistant discovery. Foods can be added and eaten by publishing on the command topic.
+//!
+//! Topics, under the `topic_prefix` of the settings:
+//!   * `status`: `online`, or `offline` once the daemon is gone
+//!   * `state`: `{"expired": 1, "close_from_expiring": 2, "far_from_expiring": 5, "total": 8}`
+//!   * `food/<id>`: the [`Food`], its inventory, best before date, days left and state
+//!   * `command`: `{"action": "add", "name": "Milk", "best_before": "+5d"}`, with an
+//!     optional `inventory` and `quantity`, or `{"action": "eat", "id": "<id>"}`, where a
+//!     `name` can replace the id to eat the first of the foods with that name
+
+use rumqttc::{Client, Event, LastWill, MqttOptions, Packet, QoS};
+use serde_derive::Deserialize;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use super::backend::{self, BestBefore, Food, FoodId, FoodState, Fridge};
+use super::config;
+use super::log;
+
+/// How often the inventories are published again, to pick up the changes made elsewhere
+const REFRESH: Duration = Duration::from_secs(60);
+
+/// Wait before reconnecting after the connection to the broker is lost
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// What the publisher thread is asked to do
+enum Publish {
+    /// Publish everything again, such as after a reconnection
+    All,
+
+    /// Publish what changed since the last time
+    Changes,
+}
+
+/// A message on the command topic
+#[derive(Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+enum Command {
+    Add {
+        name: String,
+
+        /// Any format of [`BestBefore::parse`], such as `2026-03-12` or `+5d`
+        best_before: String,
+
+        /// The inventory in use when missing
+        inventory: Option<String>,
+
+        #[serde(default = "one")]
+        quantity: u8,
+    },
+    Eat {
+        id: Option<FoodId>,
+        name: Option<String>,
+        inventory: Option<String>,
+    },
+}
+
+#[inline]
+fn one() -> u8 {
+    1
+}
+
+/// Connect to the broker in the background, unless MQTT is disabled. The connection is
+/// kept until the process ends, reconnecting whenever it is lost
+pub fn start() {
+    if !config::current().mqtt.enabled {
+        return;
+    }
+    thread::spawn(run);
+}
+
+/// Drive the connection to the broker, handling the commands and telling the publisher
+/// thread what to publish
+fn run() {
+    let config = config::current();
+    let settings = &config.mqtt;
+    let prefix = settings.topic_prefix.trim_end_matches('/').to_string();
+    let client_id = format!("food-fortress-{}", config.sync.device);
+
+    let mut options = MqttOptions::new(client_id, settings.host.trim(), settings.port);
+    options.set_keep_alive(Duration::from_secs(30));
+    options.set_last_will(LastWill::new(
+        format!("{}/status", prefix),
+        "offline",
+        QoS::AtLeastOnce,
+        true,
+    ));
+    if !settings.username.is_empty() {
+        options.set_credentials(settings.username.clone(), settings.password.clone());
+    }
+
+    let (mut client, mut connection) = Client::new(options, 64);
+    let (publisher, requests) = mpsc::channel();
+    {
+        let client = client.clone();
+        let prefix = prefix.clone();
+        thread::spawn(move || publish_loop(client, &prefix, requests));
+    }
+
+    let command_topic = format!("{}/command", prefix);
+    for notification in connection.iter() {
+        match notification {
+            Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                log::info(format!(
+                    "Connected to the MQTT broker '{}:{}'",
+                    settings.host.trim(),
+                    settings.port
+                ));
+                if let Err(err) = client.subscribe(&command_topic, QoS::AtLeastOnce) {
+                    log::warning(format!("MQTT command topic cannot be subscribed: {}", err));
+                }
+                let _ = publisher.send(Publish::All);
+            }
+            Ok(Event::Incoming(Packet::Publish(message))) if message.topic == command_topic => {
+                match serde_json::from_slice(&message.payload) {
+                    Ok(command) => match handle(command) {
+                        Ok(()) => {
+                            let _ = publisher.send(Publish::Changes);
+                        }
+                        Err(err) => log::warning(format!("MQTT command failed: {}", err)),
+                    },
+                    Err(err) => log::warning(format!("Invalid MQTT command: {}", err)),
+                }
+            }
+            Ok(_) => (),
+            Err(err) => {
+                log::warning(format!("MQTT connection to the broker lost: {}", err));
+                thread::sleep(RECONNECT_DELAY);
+            }
+        }
+    }
+}
+
+/// Publish the inventories when asked, and every [`REFRESH`], only sending the messages
+/// that changed since the last time
+fn publish_loop(mut client: Client, prefix: &str, requests: mpsc::Receiver<Publish>) {
+    // Retained payload of every topic published, to skip the ones that didn't change and
+    // to clear the ones of the foods that are gone
+    let mut published = BTreeMap::<String, String>::new();
+    loop {
+        match requests.recv_timeout(REFRESH) {
+            Ok(Publish::All) => published.clear(),
+            Ok(Publish::Changes) | Err(mpsc::RecvTimeoutError::Timeout) => (),
+            Err(mpsc::RecvTimeoutError::Disconnected) => return,
+        }
+
+        let messages = messages(prefix);
+        let gone = published
+            .keys()
+            .filter(|topic| !messages.contains_key(*topic))
+            .cloned()
+            .collect::<Vec<_>>();
+        for topic in gone {
+            // An empty retained message removes the entity from Home Assistant
+            if publish(&mut client, &topic, String::new()) {
+                published.remove(&topic);
+            }
+        }
+        for (topic, payload) in messages {
+            if published.get(&topic) != Some(&payload)
+                && publish(&mut client, &topic, payload.clone())
+            {
+                published.insert(topic, payload);
+            }
+        }
+    }
+}
+
+/// Publish a retained message. Return whether it has been queued
+fn publish(client: &mut Client, topic: &str, payload: String) -> bool {
+    match client.publish(topic, QoS::AtLeastOnce, true, payload) {
+        Ok(()) => true,
+        Err(err) => {
+            log::warning(format!(
+                "MQTT message to '{}' cannot be sent: {}",
+                topic, err
+            ));
+            false
+        }
+    }
+}
+
+/// Every retained message, from its topic to its payload: the availability, the counts per
+/// [`FoodState`], the sensor of each [`Food`] and their discovery configs
+fn messages(prefix: &str) -> BTreeMap<String, String> {
+    let config = config::current();
+    let settings = &config.mqtt;
+    let discovery = settings.discovery_prefix.trim_end_matches('/');
+    let node = config
+        .sync
+        .device
+        .chars()
+        .map(|ch| if ch.is_ascii_alphanumeric() { ch } else { '_' })
+        .collect::<String>();
+    let node = format!("food_fortress_{}", node);
+    let device = json!({
+        "identifiers": [node],
+        "name": "Food Fortress",
+        "model": config.sync.device,
+    });
+    let availability = format!("{}/status", prefix);
+    let state_topic = format!("{}/state", prefix);
+
+    let mut messages = BTreeMap::new();
+    messages.insert(availability.clone(), "online".to_string());
+
+    let foods = backend::all_foods();
+    let mut counts = BTreeMap::from([
+        (FoodState::Expired.name(), 0),
+        (FoodState::CloseFromExpiring.name(), 0),
+        (FoodState::FarFromExpiring.name(), 0),
+    ]);
+    for (_, food) in &foods {
+        *counts.entry(food.best_before.state().name()).or_default() += 1;
+    }
+    let mut state = json!(counts);
+    state["total"] = json!(foods.len());
+    messages.insert(state_topic.clone(), state.to_string());
+
+    for (state, name) in [
+        (FoodState::Expired, "Expired foods"),
+        (FoodState::CloseFromExpiring, "Foods close from expiring"),
+        (FoodState::FarFromExpiring, "Foods far from expiring"),
+    ] {
+        let key = state.name();
+        messages.insert(
+            format!("{}/sensor/{}_{}/config", discovery, node, key),
+            json!({
+                "name": name,
+                "unique_id": format!("{}_{}", node, key),
+                "state_topic": state_topic,
+                "value_template": format!("{{{{ value_json.{} }}}}", key),
+                "unit_of_measurement": "foods",
+                "icon": "mdi:fridge",
+                "availability_topic": availability,
+                "device": device,
+            })
+            .to_string(),
+        );
+    }
+
+    if !settings.publish_foods {
+        return messages;
+    }
+    for (inventory, food) in &foods {
+        let food_topic = format!("{}/food/{}", prefix, food.id);
+        messages.insert(food_topic.clone(), food_payload(inventory, food));
+        messages.insert(
+            format!("{}/sensor/{}_{}/config", discovery, node, food.id),
+            json!({
+                "name": format!("{} ({})", food.name, inventory),
+                "unique_id": format!("{}_{}", node, food.id),
+                "state_topic": food_topic,
+                "value_template": "{{ value_json.days_left }}",
+                "json_attributes_topic": food_topic,
+                "unit_of_measurement": "d",
+                "icon": "mdi:food",
+                "availability_topic": availability,
+                "device": device,
+            })
+            .to_string(),
+        );
+    }
+    messages
+}
+
+/// The payload of the sensor of a [`Food`]
+fn food_payload(inventory: &str, food: &Food) -> String {
+    json!({
+        "id": food.id,
+        "name": food.name,
+        "inventory": inventory,
+        "best_before": food.best_before.format("%Y-%m-%d"),
+        "days_left": food.best_before.days_left(),
+        "state": food.best_before.state().name(),
+        "open": food.open,
+        "category": food.category,
+        "location": food.location,
+    })
+    .to_string()
+}
+
+/// Add or eat [`Food`]s as the [`Command`] says
+fn handle(command: Command) -> Result<(), String> {
+    match command {
+        Command::Add {
+            name,
+            best_before,
+            inventory,
+            quantity,
+        } => {
+            let inventory = inventory.unwrap_or_else(|| config::current().inventory.clone());
+            let mut fridge = open_inventory(&inventory)?;
+            let best_before = BestBefore::parse(&best_before).map_err(|err| err.to_string())?;
+            let food = Food::new(name.trim().to_string(), best_before).added_today();
+            for _ in 0..quantity.max(1) {
+                fridge = fridge.add(food.clone());
+            }
+            fridge.update();
+            Ok(())
+        }
+        Command::Eat {
+            id,
+            name,
+            inventory,
+        } => {
+            let inventory = inventory.unwrap_or_else(|| config::current().inventory.clone());
+            let fridge = open_inventory(&inventory)?;
+            let food = match (id, name) {
+                (Some(id), _) => fridge.get(id).map_err(|err| err.to_string())?.clone(),
+                // The one expiring first, as in the table
+                (None, Some(name)) => fridge
+                    .named(&name)
+                    .min()
+                    .cloned()
+                    .ok_or_else(|| format!("there is no '{}' in '{}'", name, inventory))?,
+                (None, None) => return Err("'eat' needs an id or a name".to_string()),
+            };
+            fridge.eat(food.id).map_err(|err| err.to_string())?.update();
+            Ok(())
+        }
+    }
+}
+
+/// Open the [`Fridge`] of an inventory, which must exist
+fn open_inventory(inventory: &str) -> Result<Fridge, String> {
+    Fridge::open_inventory(inventory).ok_or_else(|| format!("unknown inventory '{}'", inventory))
+}
@@ -0,0 +1,128 @@
+//! The notifications mod sends desktop notifications, following the freedesktop
+//! notification spec over D-Bus, when foods become close from expiring or expire. It runs
+//! inside the GUI, or in the background with `food-fortress daemon` when the window is
+//! closed. Without a session bus, as on Windows or a headless box, notifications are
+//! disabled and the app runs as usual.
+//!
+//! The session bus is found through `DBUS_SESSION_BUS_ADDRESS`, so notifications can be
+//! tried against a stand-in bus, for instance with `dbus-run-session`.
+
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use super::alerts::AlertLevel;
+use super::backend::{self, FoodId};
+use super::config;
+use super::log;
+
+/// Set once sending a notification failed, so that we don't keep trying for nothing
+static BUS_UNAVAILABLE: AtomicBool = AtomicBool::new(false);
+
+/// The [`Notified`] remembers the state each [`Food`](backend::Food) was last notified
+/// at, so that a notification is only sent when it gets worse
+#[derive(Deserialize, Serialize, Default)]
+struct Notified {
+    foods: BTreeMap<FoodId, AlertLevel>,
+}
+
+impl Notified {
+    /// Open the [`Notified`]. A missing file simply means nothing has been notified yet,
+    /// while any other error is logged and the process aborts
+    fn open() -> Self {
+        let file = match fs::File::open(&config::current().paths.notifications) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Self::default(),
+            Err(err) => log::fatal(err),
+        };
+        serde_json::from_reader(file).unwrap_or_else(|err| log::fatal(err))
+    }
+
+    /// Update the [`Notified`], overwriting the contents of the json file
+    fn update(&self) {
+        let contents = serde_json::to_string_pretty(self).unwrap_or_else(|err| log::fatal(err));
+        fs::write(&config::current().paths.notifications, contents)
+            .unwrap_or_else(|err| log::fatal(err));
+    }
+}
+
+/// Send a notification for the foods of every inventory that became close from expiring
+/// or expired since the last check, one notification per state. Return false when
+/// notifications cannot be sent because there is no notification server
+pub fn notify_crossings() -> bool {
+    let config = config::current();
+    let settings = &config.notifications;
+    if BUS_UNAVAILABLE.load(Ordering::Relaxed) {
+        return false;
+    }
+    if !settings.enabled {
+        return true;
+    }
+
+    let foods = backend::all_foods();
+    let mut notified = Notified::open();
+    let mut expiring = Vec::new();
+    let mut expired = Vec::new();
+    for (inventory, food) in &foods {
+        let Some(level) = AlertLevel::of(food) else {
+            continue;
+        };
+        if notified.foods.get(&food.id) >= Some(&level) {
+            continue;
+        }
+        notified.foods.insert(food.id, level);
+        let line = format!(
+            "{} ({}), {}",
+            food.name,
+            inventory,
+            food.best_before.format(&config.date_format)
+        );
+        match level {
+            AlertLevel::Expiring if settings.expiring => expiring.push(line),
+            AlertLevel::Expired if settings.expired => expired.push(line),
+            _ => (),
+        }
+    }
+    notified
+        .foods
+        .retain(|id, _| foods.iter().any(|(_, food)| food.id == *id));
+
+    for (summary, lines) in [("Expired", expired), ("Expiring soon", expiring)] {
+        if lines.is_empty() {
+            continue;
+        }
+        if let Err(err) = send(summary, &lines.join("\n")) {
+            log::warning(format!(
+                "Desktop notifications are disabled for this session: {}",
+                err
+            ));
+            BUS_UNAVAILABLE.store(true, Ordering::Relaxed);
+            // Not saved, so that these foods are notified once notifications work again
+            return false;
+        }
+    }
+    notified.update();
+    true
+}
+
+/// Send a notification to the freedesktop notification server
+#[cfg(all(unix, not(target_os = "macos")))]
+fn send(summary: &str, body: &str) -> Result<(), String> {
+    notify_rust::Notification::new()
+        .appname("Food Fortress")
+        .summary(summary)
+        .body(body)
+        .icon("dialog-warning")
+        .urgency(notify_rust::Urgency::Normal)
+        .show()
+        .map(|_| ())
+        .map_err(|err| err.to_string())
+}
+
+/// There is no freedesktop notification server outside of Linux and the BSDs
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+fn send(_summary: &str, _body: &str) -> Result<(), String> {
+    Err("there is no freedesktop notification server on this system".to_string())
+}
@@ -0,0 +1,151 @@
+//! The sound mod plays the sound effects of the app. A single audio thread owns the output
+//! stream for the whole session, and the UI sends it the [`SoundEvent`]s to play. Without
+//! an audio device, as on a headless Linux box, the thread stays silent and the app runs
+//! as usual. Builds without the `audio` feature, which needs ALSA on Linux, play no sound
+//! at all.
+
+#[cfg(feature = "audio")]
+use std::fs;
+#[cfg(feature = "audio")]
+use std::io;
+#[cfg(feature = "audio")]
+use std::sync::mpsc;
+#[cfg(feature = "audio")]
+use std::sync::{Mutex, PoisonError};
+#[cfg(feature = "audio")]
+use std::thread;
+
+#[cfg(feature = "audio")]
+use super::config;
+#[cfg(feature = "audio")]
+use super::log;
+
+/// Sender to the audio thread, started the first time a sound is played
+#[cfg(feature = "audio")]
+static AUDIO_THREAD: Mutex<Option<mpsc::Sender<Sound>>> = Mutex::new(None);
+
+/// Whether the audio thread could open the output device, [`None`] until it tried
+#[cfg(feature = "audio")]
+static DEVICE: Mutex<Option<Result<(), String>>> = Mutex::new(None);
+
+/// What happened in the app, each with its own configurable sound
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SoundEvent {
+    Added,
+    Opened,
+    Eaten,
+    Discarded,
+
+    /// Foods are expiring, played at startup
+    ExpiryAlert,
+}
+
+/// A sound file to play at some volume
+#[cfg(feature = "audio")]
+struct Sound {
+    path: String,
+    volume: f32,
+}
+
+/// Play the sound of the [`SoundEvent`], unless sounds are muted or the event has no
+/// sound. Playing never blocks the UI: if the sound cannot be played for whatever reason,
+/// the error is logged and the sound skipped
+#[cfg(feature = "audio")]
+pub fn play(event: SoundEvent) {
+    let config = config::current();
+    let sound = &config.sound;
+    if !sound.enabled {
+        return;
+    }
+    let path = match event {
+        SoundEvent::Added => &sound.added,
+        SoundEvent::Opened => &sound.opened,
+        SoundEvent::Eaten => &sound.eaten,
+        SoundEvent::Discarded => &sound.discarded,
+        SoundEvent::ExpiryAlert => &sound.expiry_alert,
+    };
+    if path.trim().is_empty() {
+        return;
+    }
+
+    let sound = Sound {
+        path: path.trim().to_string(),
+        volume: sound.volume.clamp(0.0, 1.0),
+    };
+    let mut audio_thread = AUDIO_THREAD.lock().unwrap_or_else(PoisonError::into_inner);
+    let sender = audio_thread.get_or_insert_with(start_audio_thread);
+    // The thread only stops if it panicked, in which case there is nothing left to do
+    let _ = sender.send(sound);
+}
+
+/// Without the `audio` feature, sounds are never played
+#[cfg(not(feature = "audio"))]
+#[inline]
+pub fn play(_event: SoundEvent) {}
+
+/// Whether sounds can be played, for the diagnostics. The output device is opened if no
+/// sound has been played yet
+#[cfg(feature = "audio")]
+pub fn status() -> String {
+    AUDIO_THREAD
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get_or_insert_with(start_audio_thread);
+    match &*DEVICE.lock().unwrap_or_else(PoisonError::into_inner) {
+        None => "Opening the default output device".to_string(),
+        Some(Ok(())) => "Playing on the default output device".to_string(),
+        Some(Err(err)) => format!("No audio output device: {}", err),
+    }
+}
+
+/// Builds without the `audio` feature have no audio at all
+#[cfg(not(feature = "audio"))]
+pub fn status() -> String {
+    "Built without the 'audio' feature".to_string()
+}
+
+/// Start the audio thread, which opens the default output device once and plays the
+/// [`Sound`]s it receives on it
+#[cfg(feature = "audio")]
+fn start_audio_thread() -> mpsc::Sender<Sound> {
+    let (sender, receiver) = mpsc::channel::<Sound>();
+
+    // https://stackoverflow.com/questions/69393226/different-behavior-between-match-and-unwrap
+    // DO NOT REPLACE '_stream' WITH '_'
+    thread::spawn(move || {
+        let (_stream, handle) = match rodio::OutputStream::try_default() {
+            Ok((s, h)) => (s, h),
+            Err(err) => {
+                log::warning(format!(
+                    "No audio output device, sounds are disabled for this session: {}",
+                    err
+                ));
+                *DEVICE.lock().unwrap_or_else(PoisonError::into_inner) = Some(Err(err.to_string()));
+                // Keep receiving, so that senders never notice
+                for _ in receiver {}
+                return;
+            }
+        };
+
+        *DEVICE.lock().unwrap_or_else(PoisonError::into_inner) = Some(Ok(()));
+
+        for sound in receiver {
+            if let Err(err) = play_on(&handle, &sound) {
+                log::warning(format!("Sound '{}' cannot be played: {}", sound.path, err));
+            }
+        }
+    });
+    sender
+}
+
+/// Play a [`Sound`] on the output stream, without waiting for it to end
+#[cfg(feature = "audio")]
+fn play_on(handle: &rodio::OutputStreamHandle, sound: &Sound) -> Result<(), String> {
+    let file = fs::File::open(&sound.path).map_err(|err| err.to_string())?;
+    let source = rodio::Decoder::new(io::BufReader::new(file)).map_err(|err| err.to_string())?;
+    let sink = rodio::Sink::try_new(handle).map_err(|err| err.to_string())?;
+    sink.set_volume(sound.volume);
+    sink.append(source);
+    sink.detach();
+    Ok(())
+}
@@ -0,0 +1,132 @@
+//! The watcher mod notices when the data files are changed by someone else than the app,
+//! such as a script, a text editor or a sync client, so that the UI can reload them instead
+//! of overwriting the changes. It relies on the file notifications of the OS: inotify on
+//! Linux, FSEvents on macOS and ReadDirectoryChangesW on Windows.
+
+use eframe::egui;
+use notify::Watcher;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use super::config;
+use super::log;
+use super::sync::Replica;
+
+/// The [`FileWatcher`] watches the directories of the files the current inventory is made of,
+/// and of the logs the windows show
+pub struct FileWatcher {
+    /// The directories to watch
+    dirs: Vec<PathBuf>,
+
+    /// The directories actually watched, those the OS notifications are available for
+    watched: Vec<PathBuf>,
+
+    /// Set whenever something changed in a watched directory, until taken
+    changed: Arc<AtomicBool>,
+
+    /// [`None`] when the OS notifications are not available, in which case the files are
+    /// only reloaded on the next repaint
+    _watcher: Option<notify::RecommendedWatcher>,
+}
+
+impl FileWatcher {
+    /// Watch the given directories, repainting the UI when something changes in them.
+    /// Directories are watched rather than files because the files are usually replaced
+    /// by renaming a temporary file over them, which would end the watch of a file
+    pub fn new(dirs: Vec<PathBuf>, ctx: &egui::Context) -> Self {
+        let changed = Arc::new(AtomicBool::new(false));
+        let watcher = {
+            let changed = Arc::clone(&changed);
+            let ctx = ctx.clone();
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+                Ok(event) if event.kind.is_access() => (),
+                Ok(_) => {
+                    changed.store(true, Ordering::Relaxed);
+                    ctx.request_repaint();
+                }
+                Err(err) => log::warning(format!("File watcher error: {}", err)),
+            })
+        };
+
+        let mut watched = Vec::new();
+        let watcher = match watcher {
+            Ok(mut watcher) => {
+                for dir in &dirs {
+                    match watcher.watch(dir, notify::RecursiveMode::NonRecursive) {
+                        Ok(()) => watched.push(dir.clone()),
+                        Err(err) => log::warning(format!(
+                            "Changes to '{}' cannot be watched: {}",
+                            dir.display(),
+                            err
+                        )),
+                    }
+                }
+                Some(watcher)
+            }
+            Err(err) => {
+                log::warning(format!(
+                    "External changes to the data files cannot be watched: {}",
+                    err
+                ));
+                None
+            }
+        };
+
+        Self {
+            dirs,
+            watched,
+            changed,
+            _watcher: watcher,
+        }
+    }
+
+    /// The directories to watch for the inventory in use: the one of its json file and,
+    /// when sync is enabled, its synced directory. Also the ones of the intake and waste
+    /// logs and of the dismissed alerts, which the windows show
+    pub fn dirs_to_watch() -> Vec<PathBuf> {
+        let config = config::current();
+        let mut dirs = vec![parent(config.inventory_path())];
+        if let Some(replica) = Replica::of_inventory(&config.inventory) {
+            dirs.push(replica.dir().to_path_buf());
+        }
+        for path in [
+            &config.paths.intake,
+            &config.paths.waste,
+            &config.paths.alerts,
+        ] {
+            let dir = parent(path);
+            if !dirs.contains(&dir) {
+                dirs.push(dir);
+            }
+        }
+        dirs.retain(|dir| dir.is_dir());
+        dirs
+    }
+
+    /// The directories to watch
+    #[inline]
+    pub fn dirs(&self) -> &[PathBuf] {
+        &self.dirs
+    }
+
+    /// The directories actually watched
+    #[inline]
+    pub fn watched(&self) -> &[PathBuf] {
+        &self.watched
+    }
+
+    /// Returns whether something changed since the last call
+    #[inline]
+    pub fn take_changed(&self) -> bool {
+        self.changed.swap(false, Ordering::Relaxed)
+    }
+}
+
+/// The directory of a file, `.` for a bare file name
+fn parent(path: &str) -> PathBuf {
+    match Path::new(path).parent() {
+        Some(dir) if dir != Path::new("") => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
@@ -0,0 +1,347 @@
+//! The webhooks mod POSTs a json payload describing a [`Food`] to the URLs of the settings
+//! whenever it is added, opened, eaten or discarded, or becomes close from expiring or
+//! expired, for home automation. Deliveries go through an outbox persisted in a json file:
+//! a failed delivery is retried with an exponential backoff, even after a restart, until
+//! it succeeds or runs out of attempts.
+//!
+//! A worker thread does all the work, so that a slow server never blocks the UI. When the
+//! webhook has a secret, the payload is signed with HMAC-SHA256 in the
+//! `X-Food-Fortress-Signature` header, as `sha256=<hex digest>`.
+
+use fs2::FileExt;
+use hmac::{Hmac, Mac};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::sync::mpsc;
+use std::sync::{Mutex, PoisonError};
+use std::thread;
+use std::time::Duration;
+
+use super::alerts::AlertLevel;
+use super::backend::{self, today, Food, FoodEvent, FoodId};
+use super::clock;
+use super::config;
+pub use super::config::WebhookEvent;
+use super::log;
+
+/// Sender to the worker thread, started the first time it is needed
+static WORKER: Mutex<Option<mpsc::Sender<Job>>> = Mutex::new(None);
+
+/// How long a server has to answer
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Longest wait between two attempts of a delivery
+const MAX_BACKOFF_SECONDS: i64 = 24 * 60 * 60;
+
+/// What the worker thread is asked to do
+enum Job {
+    /// Queue the deliveries of an event about a [`Food`] of an inventory
+    Emit(WebhookEvent, String, Box<Food>),
+
+    /// Queue the deliveries of the expiry transitions since the last check
+    CheckTransitions,
+}
+
+/// The json payload POSTed to the webhooks
+#[derive(Serialize)]
+struct Payload<'a> {
+    event: WebhookEvent,
+
+    /// When the event happened, in RFC 3339
+    timestamp: String,
+
+    inventory: &'a str,
+    food: &'a Food,
+
+    /// Best before date of the [`Food`], as `YYYY-MM-DD`
+    best_before: String,
+    days_left: u16,
+
+    /// `expired`, `close_from_expiring` or `far_from_expiring`
+    state: &'static str,
+}
+
+/// A payload waiting to be delivered to a webhook
+#[derive(Deserialize, Serialize)]
+struct Delivery {
+    /// Sent in the `X-Food-Fortress-Delivery` header, the same for every attempt, so that
+    /// the server can ignore a payload it already received
+    id: String,
+
+    /// The webhook, whose secret is looked up in the settings when the payload is sent
+    url: String,
+
+    event: WebhookEvent,
+    body: String,
+    attempts: u32,
+
+    /// Not before this time, in milliseconds since the Unix epoch
+    next_attempt: i64,
+}
+
+/// The [`Outbox`] holds the deliveries not done yet, along with the level each [`Food`]
+/// was last seen at, so that every expiry transition is sent once
+#[derive(Deserialize, Serialize, Default)]
+struct Outbox {
+    deliveries: Vec<Delivery>,
+    levels: BTreeMap<FoodId, AlertLevel>,
+}
+
+impl Outbox {
+    /// Open the [`Outbox`], locked against the other processes of the app until the
+    /// returned lock file is dropped. A missing file simply means an empty outbox, while
+    /// any other error is logged and the process aborts
+    fn open_locked() -> (Self, fs::File) {
+        let path = &config::current().paths.webhooks;
+        let lock = fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(false)
+            .open(format!("{}.lock", path))
+            .unwrap_or_else(|err| log::fatal(err));
+        lock.lock_exclusive().unwrap_or_else(|err| log::fatal(err));
+
+        let outbox = match fs::File::open(path) {
+            Ok(file) => serde_json::from_reader(file).unwrap_or_else(|err| log::fatal(err)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Self::default(),
+            Err(err) => log::fatal(err),
+        };
+        (outbox, lock)
+    }
+
+    /// Update the [`Outbox`], overwriting the contents of the json file
+    fn update(&self) {
+        let contents = serde_json::to_string_pretty(self).unwrap_or_else(|err| log::fatal(err));
+        fs::write(&config::current().paths.webhooks, contents)
+            .unwrap_or_else(|err| log::fatal(err));
+    }
+
+    /// Queue a payload for every webhook that wants the event
+    fn queue(&mut self, event: WebhookEvent, inventory: &str, food: &Food) {
+        let config = config::current();
+        let body = serde_json::to_string(&Payload {
+            event,
+            timestamp: today().to_rfc3339(),
+            inventory,
+            food,
+            best_before: food.best_before.format("%Y-%m-%d"),
+            days_left: food.best_before.days_left(),
+            state: food.best_before.state().name(),
+        })
+        .unwrap_or_else(|err| log::fatal(err));
+
+        for hook in &config.webhooks.hooks {
+            if hook.url.trim().is_empty() || !hook.events.contains(&event) {
+                continue;
+            }
+            self.deliveries.push(Delivery {
+                id: ulid::Ulid::new().to_string(),
+                url: hook.url.trim().to_string(),
+                event,
+                body: body.clone(),
+                attempts: 0,
+                next_attempt: 0,
+            });
+        }
+    }
+
+    /// Queue the expiry transitions of the [`Food`]s of every inventory since the last
+    /// check. A [`Food`] whose best before date moved away is sent again when it comes back
+    fn check_transitions(&mut self) {
+        let foods = backend::all_foods();
+        let mut levels = BTreeMap::new();
+        for (inventory, food) in &foods {
+            let Some(level) = AlertLevel::of(food) else {
+                continue;
+            };
+            if self.levels.get(&food.id) < Some(&level) {
+                let event = match level {
+                    AlertLevel::Expiring => WebhookEvent::Expiring,
+                    AlertLevel::Expired => WebhookEvent::Expired,
+                };
+                self.queue(event, inventory, food);
+            }
+            levels.insert(food.id, level);
+        }
+        self.levels = levels;
+    }
+
+    /// Try the deliveries that are due. Return when the next delivery is due, in
+    /// milliseconds since the Unix epoch
+    fn deliver(&mut self) -> Option<i64> {
+        let config = config::current();
+        let settings = &config.webhooks;
+        // Retries are due in real time, even when pretending to be another day
+        let now = clock::system_millis();
+        self.deliveries.retain_mut(|delivery| {
+            if delivery.next_attempt > now {
+                return true;
+            }
+            let Some(hook) = settings
+                .hooks
+                .iter()
+                .find(|hook| hook.url.trim() == delivery.url)
+            else {
+                log::warning(format!(
+                    "Webhook '{}' is gone from the settings, its {:?} event is dropped",
+                    delivery.url, delivery.event
+                ));
+                return false;
+            };
+
+            delivery.attempts += 1;
+            match post(delivery, &hook.secret) {
+                Ok(()) => {
+                    log::debug(format!(
+                        "Webhook '{}' received its {:?} event",
+                        delivery.url, delivery.event
+                    ));
+                    false
+                }
+                Err((err, retry)) if retry && delivery.attempts < settings.max_attempts => {
+                    let backoff = (settings.retry_after_seconds as i64)
+                        .saturating_mul(1 << (delivery.attempts - 1).min(16))
+                        .min(MAX_BACKOFF_SECONDS);
+                    delivery.next_attempt = now + backoff * 1000;
+                    log::warning(format!(
+                        "Webhook '{}' failed, attempt {} of {}, retrying in {}s: {}",
+                        delivery.url, delivery.attempts, settings.max_attempts, backoff, err
+                    ));
+                    true
+                }
+                Err((err, _)) => {
+                    log::error(format!(
+                        "Webhook '{}' failed after {} attempts, its {:?} event is dropped: {}",
+                        delivery.url, delivery.attempts, delivery.event, err
+                    ));
+                    false
+                }
+            }
+        });
+        self.deliveries
+            .iter()
+            .map(|delivery| delivery.next_attempt)
+            .min()
+    }
+}
+
+/// Send the webhook events of every change saved to the inventories from now on, by any
+/// part of the app
+pub fn listen() {
+    backend::on_change(|inventory, event, food| {
+        let event = match event {
+            FoodEvent::Added => WebhookEvent::Added,
+            FoodEvent::Opened => WebhookEvent::Opened,
+            FoodEvent::Eaten => WebhookEvent::Eaten,
+            FoodEvent::Discarded => WebhookEvent::Discarded,
+            // Only the leftover it went into is eaten
+            FoodEvent::UsedIn(_) => return,
+        };
+        emit(event, inventory, food);
+    });
+}
+
+/// Send a webhook event about a [`Food`] of an inventory, unless webhooks are disabled.
+/// This never blocks: the payload is queued and delivered by the worker thread
+fn emit(event: WebhookEvent, inventory: &str, food: &Food) {
+    if config::current().webhooks.enabled {
+        send_job(Job::Emit(
+            event,
+            inventory.to_string(),
+            Box::new(food.clone()),
+        ));
+    }
+}
+
+/// Send the expiry transitions since the last check, unless webhooks are disabled
+pub fn check_transitions() {
+    if config::current().webhooks.enabled {
+        send_job(Job::CheckTransitions);
+    }
+}
+
+/// Send a [`Job`] to the worker thread, starting it if needed
+fn send_job(job: Job) {
+    let mut worker = WORKER.lock().unwrap_or_else(PoisonError::into_inner);
+    let sender = worker.get_or_insert_with(start_worker);
+    // The thread only stops if it panicked, in which case there is nothing left to do
+    let _ = sender.send(job);
+}
+
+/// Start the worker thread, which queues the deliveries of the [`Job`]s it receives and
+/// delivers them, waking up when a retry is due
+fn start_worker() -> mpsc::Sender<Job> {
+    let (sender, receiver) = mpsc::channel::<Job>();
+    thread::spawn(move || {
+        let mut next_due = None;
+        loop {
+            let wait = next_due.map_or(Duration::from_secs(60 * 60), |due: i64| {
+                Duration::from_millis((due - clock::system_millis()).max(0) as u64)
+            });
+            let mut jobs = match receiver.recv_timeout(wait) {
+                Ok(job) => vec![job],
+                Err(mpsc::RecvTimeoutError::Timeout) => Vec::new(),
+                Err(mpsc::RecvTimeoutError::Disconnected) => return,
+            };
+            jobs.extend(receiver.try_iter());
+
+            let (mut outbox, _lock) = Outbox::open_locked();
+            for job in jobs {
+                match job {
+                    Job::Emit(event, inventory, food) => outbox.queue(event, &inventory, &food),
+                    Job::CheckTransitions => outbox.check_transitions(),
+                }
+            }
+            // Saved before delivering, so that nothing is lost if the app is closed
+            outbox.update();
+            next_due = outbox.deliver();
+            outbox.update();
+        }
+    });
+    sender
+}
+
+/// POST the payload of a [`Delivery`]. On failure, also return whether it is worth
+/// retrying: it is not when the server refused the payload itself
+fn post(delivery: &Delivery, secret: &str) -> Result<(), (String, bool)> {
+    let mut request = ureq::post(&delivery.url)
+        .timeout(TIMEOUT)
+        .set("Content-Type", "application/json")
+        .set("User-Agent", "Food Fortress")
+        .set(
+            "X-Food-Fortress-Event",
+            &format!("{:?}", delivery.event).to_lowercase(),
+        )
+        .set("X-Food-Fortress-Delivery", &delivery.id);
+    if !secret.is_empty() {
+        request = request.set(
+            "X-Food-Fortress-Signature",
+            &signature(secret, &delivery.body),
+        );
+    }
+
+    match request.send_string(&delivery.body) {
+        Ok(_) => Ok(()),
+        Err(ureq::Error::Status(status, _)) => Err((
+            format!("the server answered {}", status),
+            // Timeouts and rate limits are worth retrying, unlike the other client errors
+            !(400..500).contains(&status) || status == 408 || status == 429,
+        )),
+        Err(err) => Err((err.to_string(), true)),
+    }
+}
+
+/// The `X-Food-Fortress-Signature` header of a payload
+fn signature(secret: &str, body: &str) -> String {
+    let mut mac = Hmac::<sha2::Sha256>::new_from_slice(secret.as_bytes())
+        .unwrap_or_else(|err| log::fatal(err));
+    mac.update(body.as_bytes());
+    let digest = mac.finalize().into_bytes();
+    let hex = digest
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<String>();
+    format!("sha256={}", hex)
+}
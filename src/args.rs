@@ -0,0 +1,101 @@
+//! Command line arguments of the app. Without arguments, the GUI opens the inventory
+//! last used, while the commands below run without it.
+
+/// Usage shown when the arguments cannot be parsed
+pub const USAGE: &str = "Usage: food-fortress [daemon | digest [--dry-run]] [--fridge NAME] \
+    [--import-products PATH] [--pretend-date YYYY-MM-DD[THH:MM]] [--freeze-clock]";
+
+/// What the app does
+#[derive(Default, PartialEq, Eq)]
+pub enum Command {
+    /// Open the window
+    #[default]
+    Gui,
+
+    /// Run without window, sending desktop notifications and the email digest when due
+    #[cfg(feature = "cli")]
+    Daemon,
+
+    /// Send the email digest right away, or only print it with `--dry-run`
+    #[cfg(all(feature = "cli", feature = "server"))]
+    Digest { dry_run: bool },
+}
+
+/// The parsed command line arguments
+#[derive(Default)]
+pub struct Args {
+    pub command: Command,
+
+    /// Name of the inventory to open instead of the one last used
+    pub fridge: Option<String>,
+
+    /// Open Food Facts CSV dump to import into the product database
+    pub import_products: Option<String>,
+
+    /// Pretend it is this date, to check the expiry logic. Given as `YYYY-MM-DD`, keeping
+    /// the time of day, or as `YYYY-MM-DDTHH:MM[:SS]`
+    pub pretend_date: Option<chrono::NaiveDateTime>,
+
+    /// Stop the clock, at the pretended date if any
+    pub freeze_clock: bool,
+}
+
+impl Args {
+    /// Parse the command line arguments of the process
+    pub fn parse() -> Result<Self, String> {
+        let mut parsed = Self::default();
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| format!("Missing value for '{}'", arg))
+            };
+            match arg.as_str() {
+                #[cfg(feature = "cli")]
+                "daemon" => parsed.command = Command::Daemon,
+                #[cfg(all(feature = "cli", feature = "server"))]
+                "digest" => parsed.command = Command::Digest { dry_run: false },
+                #[cfg(all(feature = "cli", feature = "server"))]
+                "--dry-run" => match &mut parsed.command {
+                    Command::Digest { dry_run } => *dry_run = true,
+                    _ => return Err("'--dry-run' only applies to 'digest'".to_string()),
+                },
+                #[cfg(not(feature = "cli"))]
+                "daemon" => return Err(unavailable(&arg, "the 'cli' feature")),
+                #[cfg(not(all(feature = "cli", feature = "server")))]
+                "digest" | "--dry-run" => {
+                    return Err(unavailable(&arg, "the 'cli' and 'server' features"))
+                }
+                "--fridge" => parsed.fridge = Some(value()?),
+                "--import-products" => parsed.import_products = Some(value()?),
+                "--pretend-date" => parsed.pretend_date = Some(parse_date_time(&value()?)?),
+                "--freeze-clock" => parsed.freeze_clock = true,
+                _ => return Err(format!("Unknown argument '{}'", arg)),
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+/// Parse a `YYYY-MM-DD` date, at the current time of day, or a `YYYY-MM-DDTHH:MM[:SS]`
+/// date and time
+fn parse_date_time(text: &str) -> Result<chrono::NaiveDateTime, String> {
+    let invalid = |err| format!("Invalid date '{}': {}", text, err);
+    if text.contains('T') {
+        chrono::NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M:%S")
+            .or_else(|_| chrono::NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M"))
+            .map_err(invalid)
+    } else {
+        let date = chrono::NaiveDate::parse_from_str(text, "%Y-%m-%d").map_err(invalid)?;
+        Ok(date.and_time(chrono::Local::now().time()))
+    }
+}
+
+/// Error for an argument left out of this build by its cargo features
+#[cfg(not(all(feature = "cli", feature = "server")))]
+fn unavailable(arg: &str, features: &str) -> String {
+    format!(
+        "'{}' is not available in this build, which lacks {}",
+        arg, features
+    )
+}
@@ -1,28 +1,182 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // Hide console window on Windows in release
-#![cfg(all(target_arch = "x86_64", target_os = "windows"))] // Set target os as Windows
 #![allow(non_snake_case)]
 
 mod app;
+mod args;
 
-use app::frontend::App;
+use app::clock::{self, Clock};
+use app::config;
+#[cfg(all(feature = "cli", feature = "server"))]
+use app::digest;
+#[cfg(feature = "gui")]
+use app::frontend::{App, ICON};
+#[cfg(feature = "gui")]
+use app::instance::InstanceLock;
 use app::log;
+#[cfg(all(feature = "cli", feature = "server"))]
+use app::mqtt;
+#[cfg(feature = "cli")]
+use app::notifications;
+use app::products::ProductDatabase;
+#[cfg(all(feature = "server", any(feature = "gui", feature = "cli")))]
+use app::webhooks;
+use args::{Args, Command};
+#[cfg(feature = "cli")]
+use std::thread;
+#[cfg(feature = "cli")]
+use std::time::Duration;
 
 fn main() {
-    if cfg!(target_os = "windows") {
-        eframe::run_native(
-            "Fridge",
-            eframe::NativeOptions {
-                // initial_window_size has been hardcoded but I like it that way
-                initial_window_size: Some((660.0, 550.0).into()),
-                icon_data: load_image("images\\refrigerator.png"),
-                ..Default::default()
-            },
-            Box::new(|cc| Box::new(App::new(cc))),
-        );
+    let args = match Args::parse() {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{}\n{}", err, args::USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    // `--pretend-date` and `--freeze-clock` move the whole app to another time, for this
+    // session only
+    match (args.pretend_date, args.freeze_clock) {
+        (None, false) => (),
+        (Some(now), false) => clock::set(Clock::pretending(now)),
+        (Some(now), true) => clock::set(Clock::Fixed(now)),
+        (None, true) => clock::set(Clock::Fixed(chrono::Local::now().naive_local())),
+    }
+
+    // The webhooks are sent for the changes made anywhere, from the window or from MQTT
+    #[cfg(all(feature = "server", any(feature = "gui", feature = "cli")))]
+    webhooks::listen();
+
+    // `--import-products` fills the product database and exits
+    if let Some(path) = &args.import_products {
+        import_products(path);
+        return;
+    }
+
+    // `--fridge` opens another inventory, for this session only
+    if let Some(name) = args.fridge {
+        let mut config = config::Config::clone(&config::current());
+        if !config.inventories.contains_key(&name) {
+            let known = config.inventories.keys().cloned().collect::<Vec<_>>();
+            eprintln!(
+                "Unknown fridge '{}', the known ones are: {}",
+                name,
+                known.join(", ")
+            );
+            std::process::exit(2);
+        }
+        config.inventory = name;
+        config::apply(config);
+    }
+
+    // The commands run without window
+    match args.command {
+        Command::Gui => run_gui(),
+        #[cfg(feature = "cli")]
+        Command::Daemon => run_daemon(),
+        #[cfg(all(feature = "cli", feature = "server"))]
+        Command::Digest { dry_run } => send_digest(dry_run),
+    }
+}
+
+/// Open the window, unless another instance of the app already has
+#[cfg(feature = "gui")]
+fn run_gui() {
+    // A second window would overwrite the changes of the first one. The lock is released
+    // by the OS when the process ends
+    let _lock = match InstanceLock::acquire() {
+        Ok(lock) => lock,
+        Err(err) => {
+            log::warning(format!("The app cannot start: {}", err));
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
+
+    log::info("The app started");
+    let window = config::current().window.clone();
+    eframe::run_native(
+        "Fridge",
+        eframe::NativeOptions {
+            initial_window_size: Some((window.width, window.height).into()),
+            icon_data: load_image(ICON),
+            ..Default::default()
+        },
+        Box::new(|cc| Box::new(App::new(cc))),
+    );
+}
+
+/// Builds without the `gui` feature, such as on a headless server, only run the commands
+#[cfg(not(feature = "gui"))]
+fn run_gui() {
+    eprintln!("This build of Food Fortress has no window\n{}", args::USAGE);
+    std::process::exit(2);
+}
+
+/// Without window, send the desktop notifications, the email digest and the expiry
+/// webhooks when due, and publish to MQTT, until the process is killed. Builds without
+/// the `server` feature only send the desktop notifications
+#[cfg(feature = "cli")]
+fn run_daemon() {
+    let config = config::current();
+    let serving = cfg!(feature = "server")
+        && (config.digest.enabled || config.webhooks.enabled || config.mqtt.enabled);
+    if !config.notifications.enabled && !serving {
+        eprintln!("Everything the daemon does is disabled in the settings");
+        std::process::exit(1);
+    }
+    println!("Watching for expiring foods, press Ctrl+C to stop");
+    log::info("The daemon started");
+    #[cfg(feature = "server")]
+    mqtt::start();
+
+    let mut notifying = true;
+    loop {
+        if notifying && !notifications::notify_crossings() {
+            notifying = false;
+            if !serving {
+                eprintln!("Desktop notifications are not available, see the log file");
+                std::process::exit(1);
+            }
+        }
+        #[cfg(feature = "server")]
+        {
+            digest::send_if_due();
+            webhooks::check_transitions();
+        }
+
+        let minutes = config::current().notifications.interval_minutes.max(1);
+        thread::sleep(Duration::from_secs(minutes as u64 * 60));
+    }
+}
+
+/// Send the email digest right away, or only print it when `dry_run`
+#[cfg(all(feature = "cli", feature = "server"))]
+fn send_digest(dry_run: bool) {
+    if dry_run {
+        match digest::dry_run() {
+            Ok(email) => println!("{}", email),
+            Err(err) => {
+                eprintln!("The email digest cannot be written: {}", err);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    match digest::send_now() {
+        Ok(()) => println!("The email digest has been sent"),
+        Err(err) => {
+            log::error(format!("The email digest could not be sent: {}", err));
+            eprintln!("The email digest could not be sent: {}", err);
+            std::process::exit(1);
+        }
     }
 }
 
 /// Load an image using the [`image`] crate. Return [`None`] if the image cannot be opened.
+#[cfg(feature = "gui")]
 fn load_image(path: &str) -> Option<eframe::IconData> {
     let Some(img) = image::open(path).ok() else {
         log::warning(format!("App icon '{}' could not be found", path));
@@ -38,3 +192,20 @@ fn load_image(path: &str) -> Option<eframe::IconData> {
         height,
     })
 }
+
+/// Import an Open Food Facts CSV dump into the product database
+fn import_products(path: &str) {
+    match ProductDatabase::open().import_open_food_facts(path) {
+        Ok((products, imported)) => {
+            products.update();
+            println!("Imported {} products from '{}'", imported, path);
+        }
+        Err(err) => {
+            log::warning(format!(
+                "Products could not be imported from '{}': {}",
+                path, err
+            ));
+            eprintln!("Products could not be imported from '{}': {}", path, err);
+        }
+    }
+}